@@ -0,0 +1,6 @@
+#!/bin/bash
+
+ALIAS=${1:-short}
+URL=${2:-}
+
+curl -s -G --data-urlencode "url=$URL" ${SERVER:-localhost:3000}/aliases/$ALIAS
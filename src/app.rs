@@ -1,18 +1,30 @@
 use std::io::BufRead;
-use std::{error::Error, sync::Arc};
+use std::{collections::HashSet, error::Error, sync::Arc};
 
 use crate::storage::Redis;
-use crate::validator::{Alnum, Validator};
+use crate::validator::{AllOf, Unicode, Validator};
 use crate::{
+    canonical::Canonicalizer,
+    chain::Chains,
     error,
-    generators::{Counter, RB62},
+    filter::DomainFilter,
+    generators::{Checksum, Context, Counter, KeyPool, RB62},
+    link::Link,
+    listing::{Page, Query},
+    lookup::{self, Target},
+    policy::Policy,
     storage::{Cache, Cached, Memory, Postgres, Sqlite},
+    suggest::Suggester,
+    transfer::{self, Format, OnConflict, Record, Report, Resolution},
     validator::Code,
     Generator, Shrinker, Storage,
 };
 use tokio::sync::RwLock;
 use url::Url;
 
+/// How often `App::shrink_many` tries new codes for a link.
+const MAX_ATTEMPTS: usize = 8;
+
 pub struct App<G, S> {
     pub urls: S,
     codes: G,
@@ -23,15 +35,20 @@ impl App<Counter, Memory> {
         let f = std::fs::File::open(path)?;
         let reader = std::io::BufReader::new(f);
 
-        let mut codes = Counter::default();
+        let codes = Counter::default();
         let mut urls = Memory::default();
 
+        let mut links = Vec::new();
         for line in reader.lines() {
             let url = line?.parse()?;
-            let code = codes.generate(&url);
-            urls.store(url, &code)?;
+            let code = codes.generate(&Context::new(&url))?;
+            links.push((Link::new(url), code));
         }
 
+        urls.store_many(links)
+            .into_iter()
+            .collect::<Result<(), _>>()?;
+
         Ok(Self { urls, codes })
     }
 }
@@ -40,7 +57,7 @@ impl App<RB62, Sqlite> {
     pub fn open(path: &str) -> Result<App<RB62, Sqlite>, Box<dyn Error>> {
         Ok(Self {
             urls: Sqlite::open(path)?,
-            codes: RB62,
+            codes: RB62::default(),
         })
     }
 }
@@ -51,32 +68,146 @@ impl App<RB62, Postgres> {
 
         Self {
             urls: Postgres::connect(config).await.unwrap(),
-            codes: RB62,
+            codes: RB62::default(),
         }
     }
 }
 
+impl<G: Generator, S: Storage> App<G, S> {
+    /// Like `Shrinker::shrink`, letting the generator know more about the
+    /// request.
+    pub fn shrink_with(&mut self, link: Link, context: &Context) -> Result<Code, error::Error> {
+        loop {
+            let (mut code, unique) = self.codes.generate_unique(context)?;
+
+            // In case there is a collision, we will be able to load a value
+            // using the newly generated code. Generate a new code until it's
+            // unique, unless the `Generator` already guarantees that.
+            while !unique && self.urls.load(&code).is_ok() {
+                code = self.codes.generate(context)?;
+            }
+
+            // Unique codes can still clash with a custom alias picked after
+            // they were reserved, try the next one then.
+            match self.urls.store(link.clone(), &code) {
+                Err(error::Error::Duplicate) if unique => continue,
+                result => result?,
+            }
+
+            return Ok(code);
+        }
+    }
+}
+
+impl<G: Generator, S: Storage> App<G, S> {
+    /// Like `Shrinker::shrink` for many links at once, storing them in
+    /// batches. Results are in the order of `links`.
+    pub fn shrink_many(&mut self, links: Vec<Link>) -> Vec<Result<Code, error::Error>> {
+        let mut results: Vec<Option<Result<Code, error::Error>>> =
+            links.iter().map(|_| None).collect();
+        let mut pending: Vec<usize> = (0..links.len()).collect();
+
+        // Codes that clash with stored ones are regenerated, a few times.
+        for _ in 0..MAX_ATTEMPTS {
+            if pending.is_empty() {
+                break;
+            }
+
+            let mut batched = Vec::new();
+            let mut batch = Vec::new();
+
+            for i in pending {
+                match self.codes.generate(&Context::new(&links[i].url)) {
+                    Ok(code) => {
+                        batched.push(i);
+                        batch.push((links[i].clone(), code));
+                    }
+                    Err(e) => results[i] = Some(Err(e)),
+                }
+            }
+            let codes: Vec<Code> = batch.iter().map(|(_, code)| code.clone()).collect();
+
+            let mut clashed = Vec::new();
+
+            for ((i, code), result) in batched
+                .into_iter()
+                .zip(codes)
+                .zip(self.urls.store_many(batch))
+            {
+                match result {
+                    Ok(()) => results[i] = Some(Ok(code)),
+                    Err(error::Error::Duplicate) => clashed.push(i),
+                    Err(e) => results[i] = Some(Err(e)),
+                }
+            }
+
+            pending = clashed;
+        }
+
+        results
+            .into_iter()
+            .map(|result| result.unwrap_or(Err(error::Error::Duplicate)))
+            .collect()
+    }
+}
+
 impl<G: Generator, S: Storage> Shrinker for App<G, S> {
-    fn shrink(&mut self, url: Url) -> Result<Code, error::Internal> {
-        let mut code = self.codes.generate(&url);
+    fn shrink(&mut self, url: Url) -> Result<Code, error::Error> {
+        let context = Context::new(&url);
+        self.shrink_with(Link::new(url.clone()), &context)
+    }
+
+    fn expand(&self, code: &Code) -> Result<Url, error::Error> {
+        self.urls.load(code).map(|link| link.url)
+    }
+}
+
+impl<G, S: Storage> App<G, S> {
+    /// Loads the link of a code ending in a check character made as
+    /// `checksum` says.
+    ///
+    /// Confusable characters are normalized first. If the check character
+    /// still doesn't match, single typos and adjacent swaps are tried, and
+    /// the code is corrected if exactly one of those exists. Codes that
+    /// can't have been generated, like aliases, are never corrected.
+    pub fn load_checked(&self, code: &Code, checksum: Checksum) -> Result<Link, error::Error> {
+        match self.urls.load(code) {
+            Err(error::Error::NotFound) => (),
+            result => return result,
+        }
+
+        let alphabet = checksum.alphabet;
+        let normalized = alphabet.normalize(code.as_str());
+
+        if !checksum.covers(&normalized) {
+            return Err(error::Error::NotFound);
+        }
 
-        // In case there is a collision, we will be able to load a value using
-        // the newly generated code. Generate a new code until it's unique.
-        //
-        // But this check will be vain if we have a `Generator` that guarantees
-        // unique values. Try EAFP by generating new code if storage has a
-        // failure?
-        while self.urls.load(&code).is_ok() {
-            code = self.codes.generate(&url);
+        if alphabet.verify(&normalized) {
+            return match normalized == code.as_str() {
+                true => Err(error::Error::NotFound),
+                false => self.urls.load(&Code::new(normalized)),
+            };
         }
 
-        self.urls.store(url, &code)?;
+        let mut found = alphabet
+            .corrections(&normalized)
+            .into_iter()
+            .filter_map(|candidate| self.urls.load(&Code::new(candidate)).ok());
 
-        Ok(code)
+        match (found.next(), found.next()) {
+            (Some(link), None) => Ok(link),
+            _ => Err(error::Error::Mismatch),
+        }
     }
+}
 
-    fn expand(&self, code: &Code) -> Result<Url, error::Load> {
-        self.urls.load(code)
+impl<G, S> App<G, S> {
+    pub fn with_codes<H>(self, codes: H) -> App<H, S> {
+        App {
+            urls: self.urls,
+            codes,
+        }
     }
 }
 
@@ -92,15 +223,359 @@ impl<S: Storage, G> App<G, S> {
     }
 }
 
+/// The `App` the server runs.
+pub type ServerApp = App<KeyPool<Box<dyn Generator>>, Cached<Redis, Sqlite>>;
+
+/// The checks a destination passes before it's stored, wherever it comes
+/// from.
+pub struct Checks<'a> {
+    pub canonical: &'a Canonicalizer,
+    pub chains: &'a Chains,
+    pub policy: &'a Policy,
+    pub domains: &'a DomainFilter,
+}
+
+impl Checks<'_> {
+    /// The canonical form of `url`, following our own links with `expand`,
+    /// if it passes the policy and domain filter.
+    pub fn vet(
+        &self,
+        url: Url,
+        expand: impl Fn(&Code) -> Result<Url, error::Error>,
+    ) -> Result<Url, error::Error> {
+        let url = self.canonical.apply(url);
+        let url = self.chains.resolve(url, expand)?;
+
+        self.policy.check(&url)?;
+        self.domains.check(&url)?;
+
+        Ok(url)
+    }
+
+    /// Whether the links on domain `from` in `storage` may be moved to
+    /// `to`: where they'd lead has to pass the policy and domain filter, and
+    /// can't be one of our own hosts, as the links would point at us.
+    pub fn retarget<S: Storage + ?Sized>(
+        &self,
+        storage: &S,
+        from: &str,
+        to: &str,
+    ) -> Result<(), error::Error> {
+        for (_, link) in storage.find(&Target::Domain(from.to_string()))? {
+            if let Some(url) = lookup::retarget(&link.url, from, to) {
+                self.chains.foreign(&url)?;
+                self.policy.check(&url)?;
+                self.domains.check(&url)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A link shrunk by `AppState::shrink_many`.
+pub struct Shrunk {
+    pub code: Code,
+    /// The destination as it was stored.
+    pub url: Url,
+    pub warnings: Vec<String>,
+}
+
 #[derive(Clone)]
 pub struct AppState {
-    pub app: Arc<RwLock<App<RB62, Cached<Redis, Sqlite>>>>,
+    pub app: Arc<RwLock<ServerApp>>,
     pub base_url: Url,
-    pub validator: Arc<Validator<Alnum>>,
+    pub validator: Arc<Validator<AllOf>>,
+    /// Validator for custom aliases.
+    pub aliases: Arc<Validator<AllOf>>,
+    /// Validator for prefixes of generated codes, which the generator's
+    /// filter can't reject once they're in every code.
+    pub prefixes: Arc<Validator<AllOf>>,
+    /// Knows the stored codes, to spot aliases confusable with them.
+    pub unicode: Arc<Unicode>,
+    /// Set when generated codes end in a check character.
+    pub checksum: Option<Checksum>,
+    pub policy: Arc<Policy>,
+    pub domains: Arc<DomainFilter>,
+    /// Check destinations against `domains` again on redirect, so newly
+    /// blocked domains stop resolving.
+    pub recheck: bool,
+    pub chains: Arc<Chains>,
+    pub canonical: Arc<Canonicalizer>,
+    pub suggester: Arc<Suggester>,
+    /// Bearer token admin routes require, they're refused without one.
+    pub admin_token: Option<Arc<str>>,
 }
 
 impl AppState {
     pub fn shrink_response(&self, code: &Code) -> Option<Url> {
         self.base_url.join(code.as_str()).ok()
     }
+
+    /// Shrinks many links with a single lock of the app, returning each
+    /// one's code, vetted destination and warnings in order.
+    pub async fn shrink_many(&self, links: Vec<Link>) -> Vec<Result<Shrunk, error::Error>> {
+        let mut results = Vec::with_capacity(links.len());
+        let mut vetted = Vec::new();
+
+        for link in links {
+            match self.vet(link.url.clone()).await {
+                Ok((url, warnings)) => {
+                    results.push(Ok((url.clone(), warnings)));
+                    vetted.push(Link { url, ..link });
+                }
+                Err(e) => results.push(Err(e)),
+            }
+        }
+
+        let mut codes = self.app.write().await.shrink_many(vetted).into_iter();
+
+        results
+            .into_iter()
+            .map(|result| {
+                let (url, warnings) = result?;
+                let code = codes.next().expect("a result for every vetted link")?;
+                self.unicode.register(code.as_str());
+
+                Ok(Shrunk {
+                    code,
+                    url,
+                    warnings,
+                })
+            })
+            .collect()
+    }
+
+    /// Loads the link of `code`, correcting typos if codes end in a check
+    /// character.
+    pub async fn load(&self, code: &Code) -> Result<Link, error::Error> {
+        let app = self.app.read().await;
+
+        match self.checksum {
+            Some(checksum) => app.load_checked(code, checksum),
+            None => app.urls.load(code),
+        }
+    }
+
+    pub fn checks(&self) -> Checks<'_> {
+        Checks {
+            canonical: &self.canonical,
+            chains: &self.chains,
+            policy: &self.policy,
+            domains: &self.domains,
+        }
+    }
+
+    /// Checks a destination before it's shrunk. Returns its canonical form,
+    /// following our own links, and warnings for the client.
+    pub async fn vet(&self, url: Url) -> Result<(Url, Vec<String>), error::Error> {
+        let url = {
+            let app = self.app.read().await;
+            self.checks().vet(url, |code| app.expand(code))?
+        };
+
+        let warnings = self
+            .chains
+            .is_shortener(&url)
+            .then(|| "destination is another shortener's link".to_string())
+            .into_iter()
+            .collect();
+
+        Ok((url, warnings))
+    }
+
+    /// A page of the stored links matching `query`.
+    pub async fn list(&self, query: &Query) -> Result<Page, error::Error> {
+        self.app.read().await.urls.list(query)
+    }
+
+    /// Links pointing at `target`.
+    pub async fn find(&self, target: &Target) -> Result<Vec<(Code, Link)>, error::Error> {
+        self.app.read().await.urls.find(target)
+    }
+
+    /// Moves links on domain `from` to `to`. The moved destinations have to
+    /// pass the same checks as new ones, or none are moved.
+    pub async fn retarget(&self, from: &str, to: &str) -> Result<Vec<(Code, Link)>, error::Error> {
+        let (from, to) = match (lookup::normalize_host(from), lookup::normalize_host(to)) {
+            (Some(from), Some(to)) => (from, to),
+            _ => return Err(error::Error::Malformed("invalid domain".into())),
+        };
+
+        let mut app = self.app.write().await;
+        self.checks().retarget(&app.urls, &from, &to)?;
+
+        app.urls.retarget(&from, &to)
+    }
+
+    /// Every stored link, in `format`.
+    pub async fn export(&self, format: Format) -> Result<Vec<u8>, error::Error> {
+        let mut out = Vec::new();
+        transfer::export(&self.app.read().await.urls, format, &mut out)?;
+
+        Ok(out)
+    }
+
+    /// Imports links with their codes, see `transfer::import`. Codes and
+    /// destinations are held to the rules of custom aliases.
+    pub async fn import(
+        &self,
+        records: Vec<Result<Record, String>>,
+        on_conflict: OnConflict,
+        dry_run: bool,
+    ) -> Report {
+        let mut app = self.app.write().await;
+        let records = transfer::checked(records, &app.urls, &self.aliases, &self.checks());
+        let codes: Vec<(usize, String)> = records
+            .iter()
+            .enumerate()
+            .filter_map(|(index, record)| Some((index, record.as_ref().ok()?.code.clone())))
+            .collect();
+
+        let report = transfer::import(&mut app.urls, records, &self.aliases, on_conflict, dry_run);
+
+        let failed = report
+            .conflicts
+            .iter()
+            .any(|conflict| conflict.resolution == Resolution::Failed);
+        if !dry_run && !failed {
+            let errors: HashSet<usize> = report.errors.iter().map(|error| error.index).collect();
+            let renamed =
+                report
+                    .conflicts
+                    .iter()
+                    .filter_map(|conflict| match &conflict.resolution {
+                        Resolution::Renamed { to } => Some(to),
+                        _ => None,
+                    });
+
+            for (_, code) in codes.iter().filter(|(index, _)| !errors.contains(index)) {
+                self.unicode.register(code);
+            }
+            for code in renamed {
+                self.unicode.register(code);
+            }
+        }
+
+        report
+    }
+
+    /// Whether `code` isn't used by a link yet.
+    pub async fn available(&self, code: &Code) -> Result<bool, error::Error> {
+        match self.app.read().await.expand(code) {
+            Ok(_) => Ok(false),
+            Err(error::Error::NotFound) => Ok(true),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Valid aliases close to `alias` that are still available.
+    pub async fn suggest(&self, alias: &str, url: Option<&Url>) -> Vec<Code> {
+        let mut suggestions = Vec::new();
+
+        for candidate in self.suggester.candidates(alias, url) {
+            if suggestions.len() == self.suggester.count {
+                break;
+            }
+
+            let Some(code) = self.aliases.validate(candidate) else {
+                continue;
+            };
+
+            if !suggestions.contains(&code) && self.available(&code).await.unwrap_or(false) {
+                suggestions.push(code);
+            }
+        }
+
+        suggestions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generators::Alphabet;
+
+    const CHECKSUM: Checksum = Checksum {
+        alphabet: Alphabet::UNAMBIGUOUS,
+        length: 8,
+    };
+
+    fn app(codes: &[&str]) -> App<Counter, Memory> {
+        let mut urls = Memory::default();
+        for code in codes {
+            urls.store(
+                Link::new(format!("https://blazinglyfast.net/{code}").parse().unwrap()),
+                &Code::new(code.to_string()),
+            )
+            .unwrap();
+        }
+
+        App {
+            urls,
+            codes: Counter::default(),
+        }
+    }
+
+    fn load(app: &App<Counter, Memory>, code: &str) -> Result<String, error::Error> {
+        app.load_checked(&Code::new(code.into()), CHECKSUM)
+            .map(|link| link.url.path().to_string())
+    }
+
+    #[test]
+    fn typos_in_generated_codes_are_corrected() {
+        let code = format!(
+            "hWU7Xgc{}",
+            Alphabet::UNAMBIGUOUS.check_char("hWU7Xgc").unwrap()
+        );
+        let app = app(&[&code]);
+
+        assert_eq!(
+            load(&app, &code.replacen('h', "k", 1)).unwrap(),
+            format!("/{code}")
+        );
+        assert!(matches!(
+            load(&app, "zzzzzzzz"),
+            Err(error::Error::Mismatch)
+        ));
+    }
+
+    #[test]
+    fn aliases_are_not_corrected() {
+        // An alias that happens to end in a valid check character.
+        let alias = format!("fast{}", Alphabet::UNAMBIGUOUS.check_char("fast").unwrap());
+        let app = app(&[&alias]);
+
+        assert!(matches!(
+            load(&app, &alias.replacen('f', "g", 1)),
+            Err(error::Error::NotFound)
+        ));
+    }
+
+    #[test]
+    fn links_are_not_retargeted_onto_our_host() {
+        let (canonical, chains, policy, domains) = (
+            Canonicalizer::default(),
+            Chains::new(vec!["https://blazingly.fast".parse().unwrap()], vec![]),
+            Policy::default(),
+            DomainFilter::default(),
+        );
+        let checks = Checks {
+            canonical: &canonical,
+            chains: &chains,
+            policy: &policy,
+            domains: &domains,
+        };
+        let mut urls = Memory::default();
+        urls.store(
+            Link::new("https://example.com/docs".parse().unwrap()),
+            &Code::new("blaze".into()),
+        )
+        .unwrap();
+
+        assert!(checks
+            .retarget(&urls, "example.com", "blazingly.fast")
+            .is_err());
+        assert!(checks.retarget(&urls, "example.com", "example.org").is_ok());
+    }
 }
@@ -0,0 +1,172 @@
+use std::str::FromStr;
+
+use url::Url;
+
+/// Normalization pipeline run on destinations before they are stored, so the
+/// same link written differently gets the same form.
+///
+/// Parsing a `Url` already lowercases the host, converts IDNs to punycode,
+/// drops default ports and resolves `.` and `..` segments for http(s), the
+/// steps here cover the rest.
+pub struct Canonicalizer {
+    steps: Vec<Step>,
+    tracking: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Step {
+    /// Drops tracking query parameters like `utm_source` or `fbclid`.
+    StripTracking,
+    /// Sorts query parameters by name.
+    SortQuery,
+    /// Drops a `?` without any parameters.
+    TrimEmptyQuery,
+    /// Drops a `#` without a fragment.
+    TrimEmptyFragment,
+}
+
+pub const DEFAULT_STEPS: &[Step] = &[
+    Step::StripTracking,
+    Step::TrimEmptyQuery,
+    Step::TrimEmptyFragment,
+];
+
+/// Query parameters only used to track where a click came from. A trailing
+/// `*` matches any parameter starting with the rest.
+pub const TRACKING_PARAMS: &[&str] = &[
+    "utm_*", "fbclid", "gclid", "dclid", "gbraid", "wbraid", "msclkid", "yclid", "igshid",
+    "mc_cid", "mc_eid", "_hsenc", "_hsmi", "mkt_tok",
+];
+
+impl FromStr for Step {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "strip-tracking" => Ok(Step::StripTracking),
+            "sort-query" => Ok(Step::SortQuery),
+            "trim-empty-query" => Ok(Step::TrimEmptyQuery),
+            "trim-empty-fragment" => Ok(Step::TrimEmptyFragment),
+            _ => Err(format!("unknown canonicalization step `{s}`")),
+        }
+    }
+}
+
+impl Canonicalizer {
+    pub fn new(steps: Vec<Step>, tracking: Vec<String>) -> Self {
+        Self { steps, tracking }
+    }
+
+    fn is_tracking(&self, param: &str) -> bool {
+        let name = param.split('=').next().unwrap_or_default();
+
+        self.tracking
+            .iter()
+            .any(|pattern| match pattern.strip_suffix('*') {
+                Some(prefix) => name.starts_with(prefix),
+                None => name == pattern,
+            })
+    }
+
+    /// Rewrites the raw query parameters, keeping their original encoding.
+    fn edit_query(url: &mut Url, edit: impl FnOnce(&mut Vec<&str>)) {
+        let Some(query) = url.query() else {
+            return;
+        };
+
+        let mut params: Vec<&str> = query.split('&').filter(|p| !p.is_empty()).collect();
+        edit(&mut params);
+
+        let query = params.join("&");
+        url.set_query(Some(&query));
+    }
+
+    pub fn apply(&self, mut url: Url) -> Url {
+        for step in &self.steps {
+            match step {
+                Step::StripTracking => {
+                    Self::edit_query(&mut url, |params| params.retain(|p| !self.is_tracking(p)))
+                }
+                Step::SortQuery => Self::edit_query(&mut url, |params| {
+                    params.sort_by_key(|p| p.split('=').next().unwrap_or_default())
+                }),
+                Step::TrimEmptyQuery => {
+                    if url.query() == Some("") {
+                        url.set_query(None);
+                    }
+                }
+                Step::TrimEmptyFragment => {
+                    if url.fragment() == Some("") {
+                        url.set_fragment(None);
+                    }
+                }
+            }
+        }
+
+        url
+    }
+}
+
+impl Default for Canonicalizer {
+    fn default() -> Self {
+        Self::new(
+            DEFAULT_STEPS.to_vec(),
+            TRACKING_PARAMS.iter().map(|p| p.to_string()).collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn canonical(url: &str) -> String {
+        Canonicalizer::default().apply(url.parse().unwrap()).into()
+    }
+
+    #[test]
+    fn example_from_the_request() {
+        assert_eq!(
+            canonical("https://Example.com:443/a/../b?utm_source=x#"),
+            "https://example.com/b"
+        );
+    }
+
+    #[test]
+    fn idn_becomes_punycode() {
+        assert_eq!(
+            canonical("https://bücher.example/"),
+            "https://xn--bcher-kva.example/"
+        );
+    }
+
+    #[test]
+    fn other_params_are_kept_in_order_and_encoding() {
+        assert_eq!(
+            canonical("https://example.com/?q=a%20b&fbclid=1&page=2&utm_medium=mail"),
+            "https://example.com/?q=a%20b&page=2"
+        );
+    }
+
+    #[test]
+    fn non_empty_fragment_is_kept() {
+        assert_eq!(
+            canonical("https://example.com/#top"),
+            "https://example.com/#top"
+        );
+    }
+
+    #[test]
+    fn query_can_be_sorted() {
+        let canonicalizer = Canonicalizer::new(vec![Step::SortQuery], Vec::new());
+        let url = canonicalizer.apply("https://example.com/?b=2&a=1&utm_x=3".parse().unwrap());
+
+        assert_eq!(url.as_str(), "https://example.com/?a=1&b=2&utm_x=3");
+    }
+
+    #[test]
+    fn parses_step_names() {
+        assert_eq!("sort-query".parse(), Ok(Step::SortQuery));
+        assert!("lowercase-path".parse::<Step>().is_err());
+    }
+}
@@ -0,0 +1,229 @@
+use std::collections::HashSet;
+use std::fmt::Display;
+
+use url::Url;
+
+use crate::{error, validator::Code};
+
+/// Spots destinations that are short links themselves: our own, which get
+/// followed to where they finally lead, and other shorteners', which get
+/// flagged.
+pub struct Chains {
+    /// Base URLs our links are served under.
+    own: Vec<Url>,
+    /// Hosts of third-party shorteners.
+    shorteners: Vec<String>,
+}
+
+/// Why a destination pointing back at us can't be shrunk.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Loop {
+    /// Following the links leads back to one seen before.
+    Cycle(String),
+    /// The destination is one of our short links, but it doesn't exist.
+    Unknown(String),
+    /// The destination is on our host, but isn't a short link.
+    SelfReference,
+}
+
+/// Hosts of well-known URL shorteners.
+pub const KNOWN_SHORTENERS: &[&str] = &[
+    "bit.ly",
+    "buff.ly",
+    "cutt.ly",
+    "goo.gl",
+    "is.gd",
+    "kutt.it",
+    "ow.ly",
+    "rb.gy",
+    "rebrand.ly",
+    "s.id",
+    "shorturl.at",
+    "t.co",
+    "t.ly",
+    "tiny.cc",
+    "tinyurl.com",
+];
+
+/// Gives up following our own links after this many hops.
+const MAX_HOPS: usize = 16;
+
+impl Display for Loop {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Loop::Cycle(code) => write!(f, "link `{code}` redirects back to itself"),
+            Loop::Unknown(code) => write!(f, "link `{code}` points at a missing short link"),
+            Loop::SelfReference => write!(f, "destination points at this shortener"),
+        }
+    }
+}
+
+impl std::error::Error for Loop {}
+
+impl Chains {
+    pub fn new(own: Vec<Url>, shorteners: Vec<String>) -> Self {
+        Self {
+            own,
+            shorteners: shorteners
+                .into_iter()
+                .map(|host| host.trim().to_lowercase())
+                .collect(),
+        }
+    }
+
+    /// Whether `url` is on the host (and port) of one of our base URLs.
+    fn is_own(&self, url: &Url) -> Option<&Url> {
+        self.own
+            .iter()
+            .find(|base| base.host_str() == url.host_str() && base.port() == url.port())
+    }
+
+    /// The code of our own short link `url` points at, if it's one of ours.
+    fn own_code(&self, url: &Url) -> Option<Result<Code, Loop>> {
+        let base = self.is_own(url)?;
+
+        let code = url
+            .path()
+            .strip_prefix(base.path())
+            .map(|rest| rest.trim_start_matches('/'))
+            .filter(|code| !code.is_empty() && !code.contains('/'));
+
+        Some(
+            code.map(|code| Code::new(code.to_string()))
+                .ok_or(Loop::SelfReference),
+        )
+    }
+
+    /// Follows our own short links until one leads elsewhere.
+    pub fn resolve(
+        &self,
+        mut url: Url,
+        expand: impl Fn(&Code) -> Result<Url, error::Error>,
+    ) -> Result<Url, Loop> {
+        let mut seen = HashSet::new();
+
+        while let Some(code) = self.own_code(&url) {
+            let code = code?;
+
+            if !seen.insert(code.clone()) || seen.len() > MAX_HOPS {
+                return Err(Loop::Cycle(code.as_str().to_string()));
+            }
+
+            url = expand(&code).map_err(|_| Loop::Unknown(code.as_str().to_string()))?;
+        }
+
+        Ok(url)
+    }
+
+    /// Fails if `url` is on our host, for destinations that are stored
+    /// without being followed, like the ones of moved links.
+    pub fn foreign(&self, url: &Url) -> Result<(), Loop> {
+        match self.is_own(url) {
+            Some(_) => Err(Loop::SelfReference),
+            None => Ok(()),
+        }
+    }
+
+    /// Whether `url` is a link of a known third-party shortener.
+    pub fn is_shortener(&self, url: &Url) -> bool {
+        let Some(host) = url.host_str() else {
+            return false;
+        };
+
+        let host = host.trim_start_matches("www.");
+        self.shorteners.iter().any(|shortener| shortener == host)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn chains() -> Chains {
+        Chains::new(
+            vec!["http://localhost:3000".parse().unwrap()],
+            KNOWN_SHORTENERS.iter().map(|s| s.to_string()).collect(),
+        )
+    }
+
+    fn resolve(links: &[(&str, &str)], url: &str) -> Result<String, Loop> {
+        let links: HashMap<Code, Url> = links
+            .iter()
+            .map(|(code, url)| (Code::new(code.to_string()), url.parse().unwrap()))
+            .collect();
+
+        chains()
+            .resolve(url.parse().unwrap(), |code| {
+                links.get(code).cloned().ok_or(error::Error::NotFound)
+            })
+            .map(String::from)
+    }
+
+    #[test]
+    fn external_urls_are_kept() {
+        assert_eq!(
+            resolve(&[], "https://blazinglyfast.net/"),
+            Ok("https://blazinglyfast.net/".into())
+        );
+    }
+
+    #[test]
+    fn own_links_are_followed() {
+        let links = [
+            ("a", "http://localhost:3000/b"),
+            ("b", "https://blazinglyfast.net/"),
+        ];
+
+        assert_eq!(
+            resolve(&links, "http://localhost:3000/a"),
+            Ok("https://blazinglyfast.net/".into())
+        );
+    }
+
+    #[test]
+    fn cycles_are_rejected() {
+        let links = [
+            ("a", "http://localhost:3000/b"),
+            ("b", "http://localhost:3000/a"),
+        ];
+
+        assert_eq!(
+            resolve(&links, "http://localhost:3000/a"),
+            Err(Loop::Cycle("a".into()))
+        );
+    }
+
+    #[test]
+    fn missing_links_are_rejected() {
+        assert_eq!(
+            resolve(&[], "http://localhost:3000/blaze"),
+            Err(Loop::Unknown("blaze".into()))
+        );
+    }
+
+    #[test]
+    fn non_links_on_our_host_are_rejected() {
+        assert_eq!(
+            resolve(&[], "http://localhost:3000/"),
+            Err(Loop::SelfReference)
+        );
+        assert_eq!(
+            resolve(&[], "http://localhost:3000/a/b"),
+            Err(Loop::SelfReference)
+        );
+    }
+
+    #[test]
+    fn other_ports_are_not_ours() {
+        assert!(resolve(&[], "http://localhost:8080/a").is_ok());
+    }
+
+    #[test]
+    fn flags_known_shorteners() {
+        assert!(chains().is_shortener(&"https://bit.ly/abc".parse().unwrap()));
+        assert!(chains().is_shortener(&"https://www.tinyurl.com/abc".parse().unwrap()));
+        assert!(!chains().is_shortener(&"https://blazinglyfast.net/".parse().unwrap()));
+    }
+}
@@ -0,0 +1,316 @@
+use std::{
+    error::Error,
+    fs::File,
+    io::{self, BufWriter, Read, Write},
+    path::PathBuf,
+};
+
+use shrink::{
+    app::Checks,
+    error,
+    link::Link,
+    lookup::{self, Target},
+    storage::{Cached, Postgres, Redis, Sqlite},
+    transfer::{
+        self,
+        foreign::{self, Source},
+        mirror::{self, Kind},
+        Format, OnConflict,
+    },
+    validator::{AllOf, Code, Validator},
+    Storage,
+};
+
+const USAGE: &str = "usage: shrink [serve]
+       shrink [--storage STORAGE] lookup (--url URL | --host HOST | --domain DOMAIN)
+       shrink [--storage STORAGE] retarget FROM TO
+       shrink [--storage STORAGE] export [--format FORMAT] [FILE]
+       shrink [--storage STORAGE] import [--format FORMAT | --from SOURCE] [--on-conflict POLICY] [--dry-run] FILE
+       shrink [--storage STORAGE] mirror KIND PATH
+
+STORAGE is a SQLite file (default: data/urls.db), or a postgres:// or redis:// URL.
+FORMAT is csv, json or ndjson, by default FILE's extension. FILE - is stdin or stdout.
+SOURCE is yourls-sql, yourls-csv, bitly or kutt, another shortener's export.
+POLICY is skip (default), overwrite, rename or fail.
+KIND is nginx, apache or redirects, written to the file PATH, or html, a directory.";
+
+/// What the binary was asked to do, and on which storage.
+pub struct Args {
+    storage: Option<String>,
+    pub command: Command,
+}
+
+pub enum Command {
+    Serve,
+    /// Print the links pointing at a target.
+    Lookup(Target),
+    /// Move links from one domain to another.
+    Retarget {
+        from: String,
+        to: String,
+    },
+    Export {
+        format: Format,
+        path: String,
+    },
+    Import {
+        input: Input,
+        path: String,
+        on_conflict: OnConflict,
+        dry_run: bool,
+    },
+    /// Bring a static config serving the links up to date.
+    Mirror {
+        kind: Kind,
+        path: PathBuf,
+    },
+}
+
+/// What's imported: records in one of shrink's formats, or another
+/// shortener's export.
+pub enum Input {
+    Records(Format),
+    Foreign(Source),
+}
+
+/// Options and positional arguments of a command.
+struct Options<'a> {
+    format: Option<Format>,
+    from: Option<Source>,
+    on_conflict: OnConflict,
+    dry_run: bool,
+    positional: Vec<&'a str>,
+}
+
+impl<'a> Options<'a> {
+    fn parse(args: &[&'a str]) -> Option<Self> {
+        let mut options = Options {
+            format: None,
+            from: None,
+            on_conflict: OnConflict::default(),
+            dry_run: false,
+            positional: Vec::new(),
+        };
+        let mut args = args.iter();
+
+        while let Some(&arg) = args.next() {
+            match arg {
+                "--format" => options.format = Some(args.next()?.parse().ok()?),
+                "--from" => options.from = Some(args.next()?.parse().ok()?),
+                "--on-conflict" => options.on_conflict = args.next()?.parse().ok()?,
+                "--dry-run" => options.dry_run = true,
+                _ if arg.starts_with("--") => return None,
+                _ => options.positional.push(arg),
+            }
+        }
+
+        Some(options)
+    }
+
+    /// The file argument, stdin or stdout if there's none.
+    fn path(&self) -> Option<String> {
+        match self.positional.as_slice() {
+            [] => Some("-".into()),
+            [path] => Some(path.to_string()),
+            _ => None,
+        }
+    }
+
+    /// The file argument and its format.
+    fn file(&self) -> Option<(Format, String)> {
+        let path = self.path()?;
+        let format = self.format.or_else(|| Format::of_path(&path))?;
+
+        Some((format, path))
+    }
+}
+
+impl Args {
+    /// Parses the arguments after the binary's name, failing with the
+    /// usage.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let args: Vec<String> = args.into_iter().collect();
+        let mut args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+        let storage = match args.as_slice() {
+            ["--storage", storage, ..] => {
+                let storage = storage.to_string();
+                args.drain(..2);
+                Some(storage)
+            }
+            _ => None,
+        };
+
+        Self::command(&args)
+            .map(|command| Args { storage, command })
+            .ok_or_else(|| USAGE.to_string())
+    }
+
+    fn command(args: &[&str]) -> Option<Command> {
+        let target = match args {
+            [] | ["serve"] => return Some(Command::Serve),
+            ["retarget", from, to] => {
+                return Some(Command::Retarget {
+                    from: lookup::normalize_host(from)?,
+                    to: lookup::normalize_host(to)?,
+                })
+            }
+            ["export", options @ ..] => {
+                let (format, path) = Options::parse(options)?.file()?;
+                return Some(Command::Export { format, path });
+            }
+            ["import", options @ ..] => {
+                let options = Options::parse(options)?;
+                let (input, path) = match (options.from, options.format) {
+                    (Some(source), None) => (Input::Foreign(source), options.path()?),
+                    (None, _) => {
+                        let (format, path) = options.file()?;
+                        (Input::Records(format), path)
+                    }
+                    (Some(_), Some(_)) => return None,
+                };
+                return Some(Command::Import {
+                    input,
+                    path,
+                    on_conflict: options.on_conflict,
+                    dry_run: options.dry_run,
+                });
+            }
+            ["mirror", kind, path] => {
+                return Some(Command::Mirror {
+                    kind: kind.parse().ok()?,
+                    path: PathBuf::from(path),
+                })
+            }
+            ["lookup", "--url", url] => url.parse().ok().map(Target::Url),
+            ["lookup", "--host", host] => Target::host(host),
+            ["lookup", "--domain", domain] => Target::domain(domain),
+            _ => None,
+        };
+
+        target.map(Command::Lookup)
+    }
+
+    /// Runs anything but `Serve`, on the SQLite file at `database` unless
+    /// another storage was given, with codes case-insensitive if
+    /// `case_insensitive`. Imported codes must pass `aliases`, and imported
+    /// and retargeted destinations `checks`, as on the server.
+    pub async fn run(
+        self,
+        database: &str,
+        case_insensitive: bool,
+        aliases: &Validator<AllOf>,
+        checks: &Checks<'_>,
+    ) -> Result<(), Box<dyn Error>> {
+        let storage = self.storage.as_deref().unwrap_or(database);
+        let redis = matches!(scheme(storage), Some("redis" | "rediss"));
+        // Redis keeps links only as long as they're cached.
+        if redis && matches!(self.command, Command::Import { .. }) {
+            return Err("links can't be imported into Redis, it's only a cache".into());
+        }
+
+        let mut urls = open(storage, case_insensitive).await?;
+
+        match self.command {
+            Command::Serve => unreachable!("the server isn't run from here"),
+            Command::Lookup(target) => print(urls.find(&target.canonical(checks.canonical))?),
+            Command::Retarget { from, to } => {
+                checks.retarget(&*urls, &from, &to)?;
+                retarget(urls, redis, &from, &to)?
+            }
+            Command::Export { format, path } => {
+                let out: Box<dyn Write> = match path.as_str() {
+                    "-" => Box::new(io::stdout().lock()),
+                    path => Box::new(File::create(path)?),
+                };
+
+                let count = transfer::export(&urls, format, BufWriter::new(out))?;
+                eprintln!("Exported {count} links");
+            }
+            Command::Import {
+                input,
+                path,
+                on_conflict,
+                dry_run,
+            } => {
+                let file: Box<dyn Read> = match path.as_str() {
+                    "-" => Box::new(io::stdin().lock()),
+                    path => Box::new(File::open(path)?),
+                };
+
+                let records = match input {
+                    Input::Records(format) => transfer::read(format, file)?,
+                    Input::Foreign(source) => foreign::read(source, file, aliases)?,
+                };
+                let records = transfer::checked(records, &urls, aliases, checks);
+                let report = transfer::import(&mut urls, records, aliases, on_conflict, dry_run);
+                println!("{}", serde_json::to_string_pretty(&report)?);
+
+                if !report.ok() {
+                    return Err("import didn't go through for every link".into());
+                }
+            }
+            Command::Mirror { kind, path } => {
+                let report = mirror::mirror(&urls, kind, &path)?;
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Moves the links on domain `from` to `to` and prints them.
+fn retarget(
+    mut urls: Box<dyn Storage>,
+    redis: bool,
+    from: &str,
+    to: &str,
+) -> Result<(), error::Error> {
+    if redis {
+        print(urls.retarget(from, to)?);
+        return Ok(());
+    }
+
+    // Retargeted links are cached as they are now, if the cache is up.
+    match Redis::from_env() {
+        Ok(cache) => {
+            let mut urls = Cached {
+                cache,
+                storage: urls,
+            };
+            print(urls.retarget(from, to)?)
+        }
+        Err(e) => {
+            eprintln!("Cache unavailable, it may serve old destinations for a while: {e}");
+            print(urls.retarget(from, to)?)
+        }
+    }
+
+    Ok(())
+}
+
+fn scheme(storage: &str) -> Option<&str> {
+    storage.split_once("://").map(|(scheme, _)| scheme)
+}
+
+/// Opens a SQLite file, or a `postgres://` or `redis://` URL. Redis has no
+/// case-insensitive codes, its codes are always looked up as they are.
+async fn open(storage: &str, case_insensitive: bool) -> Result<Box<dyn Storage>, Box<dyn Error>> {
+    Ok(match (scheme(storage), case_insensitive) {
+        (Some("postgres" | "postgresql"), false) => Box::new(Postgres::connect(storage).await?),
+        (Some("postgres" | "postgresql"), true) => {
+            Box::new(Postgres::connect(storage).await?.case_insensitive()?)
+        }
+        (Some("redis" | "rediss"), _) => Box::new(Redis::connect(storage)?),
+        (_, false) => Box::new(Sqlite::open(storage)?),
+        (_, true) => Box::new(Sqlite::open(storage)?.case_insensitive()?),
+    })
+}
+
+/// Prints links one per line, code and destination separated by a tab.
+fn print(links: Vec<(Code, Link)>) {
+    for (code, link) in links {
+        println!("{}\t{}", code.as_str(), link.url);
+    }
+}
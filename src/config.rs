@@ -1,8 +1,60 @@
+use std::path::PathBuf;
+
+use shrink::{
+    canonical::{Step, DEFAULT_STEPS, TRACKING_PARAMS},
+    chain::KNOWN_SHORTENERS,
+};
 use url::Url;
 
 pub struct Config {
     pub port: u16,
     pub server_url: Url,
+    /// Number of words in generated codes, random codes are used if unset.
+    pub words: Option<usize>,
+    /// Separator between the words of generated codes.
+    pub separator: char,
+    /// Generate codes without confusable characters (`0/O`, `1/l/I`).
+    pub unambiguous: bool,
+    /// Append a check character to generated codes.
+    pub check: bool,
+    /// File with extra words to keep out of codes, one per line.
+    pub blocklist: Option<String>,
+    /// Reject custom aliases containing blocked words.
+    pub filter_aliases: bool,
+    /// Low and high-water marks of the pre-generated code pool, if enabled.
+    pub key_pool: Option<(usize, usize)>,
+    /// Schemes destinations may use.
+    pub schemes: Vec<String>,
+    /// Ports destinations may set explicitly, any if `None`.
+    pub ports: Option<Vec<u16>>,
+    /// Allow destinations on private, loopback and link-local hosts.
+    pub allow_private: bool,
+    /// File of blocked destination domains.
+    pub domain_blocklist: Option<PathBuf>,
+    /// File of the only destination domains that may be shrunk.
+    pub domain_allowlist: Option<PathBuf>,
+    /// Check destinations against the domain lists on redirect too.
+    pub recheck: bool,
+    /// Other base URLs our links are reachable under, besides `server_url`.
+    pub own_urls: Vec<Url>,
+    /// Hosts of third-party shorteners to flag.
+    pub shorteners: Vec<String>,
+    /// Canonicalization steps run on destinations.
+    pub canonical: Vec<Step>,
+    /// Query parameters stripped by `Step::StripTracking`.
+    pub tracking: Vec<String>,
+    /// Extra words custom aliases can't be.
+    pub reserved: Vec<String>,
+    /// Length limits of custom aliases, in characters.
+    pub alias_length: (usize, usize),
+    /// Whether codes are looked up ignoring case.
+    pub case_insensitive: bool,
+    /// Validation rules for codes, see `validator::parse`.
+    pub code_rules: Option<String>,
+    /// Validation rules for custom aliases, see `validator::parse`.
+    pub alias_rules: Option<String>,
+    /// Bearer token for the admin routes, which are refused if unset.
+    pub admin_token: Option<String>,
 }
 
 impl Default for Config {
@@ -14,6 +66,29 @@ impl Default for Config {
         Self {
             port,
             server_url: base,
+            words: None,
+            separator: '-',
+            unambiguous: false,
+            check: false,
+            blocklist: None,
+            filter_aliases: true,
+            key_pool: None,
+            schemes: vec!["http".into(), "https".into()],
+            ports: None,
+            allow_private: false,
+            domain_blocklist: None,
+            domain_allowlist: None,
+            recheck: false,
+            own_urls: Vec::new(),
+            shorteners: known_shorteners(),
+            canonical: default_steps(),
+            tracking: tracking_params(),
+            reserved: Vec::new(),
+            alias_length: (3, 64),
+            case_insensitive: false,
+            code_rules: None,
+            alias_rules: None,
+            admin_token: None,
         }
     }
 }
@@ -22,11 +97,62 @@ fn read<R>(name: &'static str, f: impl Fn(String) -> Option<R>) -> Option<R> {
     std::env::var(name).ok().and_then(f)
 }
 
+fn known_shorteners() -> Vec<String> {
+    KNOWN_SHORTENERS.iter().map(|s| s.to_string()).collect()
+}
+
+fn default_steps() -> Vec<Step> {
+    DEFAULT_STEPS.to_vec()
+}
+
+fn tracking_params() -> Vec<String> {
+    TRACKING_PARAMS.iter().map(|s| s.to_string()).collect()
+}
+
+/// Parses a comma-separated list, `None` if any item doesn't parse.
+fn list<T: std::str::FromStr>(v: &str) -> Option<Vec<T>> {
+    v.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(|item| item.parse().ok())
+        .collect()
+}
+
 impl Config {
     pub fn from_env() -> Option<Self> {
         let config = Config {
             port: read("PORT", |v| v.parse().ok())?,
             server_url: read("SERVER_URL", |url| url.parse().ok())?,
+            words: read("WORD_CODES", |v| v.parse().ok()),
+            separator: read("WORD_SEPARATOR", |v| v.parse().ok()).unwrap_or('-'),
+            unambiguous: read("UNAMBIGUOUS_CODES", |v| v.parse().ok()).unwrap_or_default(),
+            check: read("CHECK_CHARACTER", |v| v.parse().ok()).unwrap_or_default(),
+            blocklist: read("BLOCKLIST", Some),
+            filter_aliases: read("FILTER_ALIASES", |v| v.parse().ok()).unwrap_or(true),
+            key_pool: read("KEY_POOL_HIGH", |v| v.parse().ok()).map(|high: usize| {
+                let low = read("KEY_POOL_LOW", |v| v.parse().ok()).unwrap_or(high / 4);
+                (low, high)
+            }),
+            schemes: read("ALLOWED_SCHEMES", |v| list(&v))
+                .unwrap_or_else(|| vec!["http".into(), "https".into()]),
+            ports: read("ALLOWED_PORTS", |v| list(&v)),
+            allow_private: read("ALLOW_PRIVATE_HOSTS", |v| v.parse().ok()).unwrap_or_default(),
+            domain_blocklist: read("DOMAIN_BLOCKLIST", |v| Some(v.into())),
+            domain_allowlist: read("DOMAIN_ALLOWLIST", |v| Some(v.into())),
+            recheck: read("RECHECK_ON_REDIRECT", |v| v.parse().ok()).unwrap_or_default(),
+            own_urls: read("OWN_URLS", |v| list(&v)).unwrap_or_default(),
+            shorteners: read("KNOWN_SHORTENERS", |v| list(&v)).unwrap_or_else(known_shorteners),
+            canonical: read("CANONICALIZE", |v| list(&v)).unwrap_or_else(default_steps),
+            tracking: read("TRACKING_PARAMS", |v| list(&v)).unwrap_or_else(tracking_params),
+            reserved: read("RESERVED_ALIASES", |v| list(&v)).unwrap_or_default(),
+            alias_length: (
+                read("ALIAS_MIN_LENGTH", |v| v.parse().ok()).unwrap_or(3),
+                read("ALIAS_MAX_LENGTH", |v| v.parse().ok()).unwrap_or(64),
+            ),
+            case_insensitive: read("CASE_INSENSITIVE", |v| v.parse().ok()).unwrap_or(false),
+            code_rules: read("CODE_RULES", Some),
+            alias_rules: read("ALIAS_RULES", Some),
+            admin_token: read("ADMIN_TOKEN", |v| (!v.is_empty()).then_some(v)),
         };
 
         Some(config)
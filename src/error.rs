@@ -1,150 +1,279 @@
-use std::error::Error;
 use std::fmt::Display;
 
-use axum::response::IntoResponse;
+use axum::{
+    extract::rejection::{JsonRejection, PathRejection, QueryRejection},
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Json,
+};
 use r2d2_postgres::postgres;
 use r2d2_sqlite::rusqlite;
 
-#[derive(Debug)]
-pub struct Duplicate;
-#[derive(Debug)]
-pub struct NotFound;
-#[derive(Debug)]
-pub struct Internal(pub String);
-#[derive(Debug)]
-pub struct BadAlias;
+use crate::validator::Failure;
 
-#[derive(Debug)]
-pub enum Storage {
+/// Everything that can go wrong shrinking, storing or expanding a link.
+#[derive(Clone, Debug)]
+pub enum Error {
+    /// A code from a request didn't pass validation.
+    Invalid {
+        /// What the code is, e.g. `alias`.
+        field: &'static str,
+        failure: Failure,
+    },
+    /// The destination URL is not allowed.
+    Rejected(String),
+    /// The request couldn't be parsed.
+    Malformed(String),
+    /// The check character of the code doesn't match and it couldn't be
+    /// corrected.
+    Mismatch,
+    /// The code is already used.
     Duplicate,
-    BadAlias,
+    NotFound,
+    /// The destination has been blocked since the code was created.
+    Blocked,
+    /// An admin route was requested without the admin token.
+    Unauthorized,
+    /// A service links are kept in can't be reached right now.
+    Unavailable(String),
     Internal(String),
 }
 
+/// Codes that differ only by case, grouped, keeping a database from being
+/// switched to case-insensitive codes.
 #[derive(Debug)]
-pub enum Load {
-    NotFound,
-    BadAlias,
-    Internal(String),
+pub struct CaseConflicts(pub Vec<Vec<String>>);
+
+/// RFC 7807 problem details, as sent to clients.
+#[derive(serde::Serialize)]
+pub struct Problem {
+    #[serde(rename = "type")]
+    kind: String,
+    title: &'static str,
+    status: u16,
+    /// Stable, machine-readable error code.
+    code: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    detail: Option<String>,
 }
 
-impl Display for Duplicate {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "duplicate entry")
+impl Error {
+    /// Stable, machine-readable code, safe to match on in clients.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::Invalid { .. } => "invalid-code",
+            Error::Rejected(_) => "rejected-destination",
+            Error::Malformed(_) => "malformed-request",
+            Error::Mismatch => "check-mismatch",
+            Error::Duplicate => "duplicate-code",
+            Error::NotFound => "not-found",
+            Error::Blocked => "blocked-destination",
+            Error::Unauthorized => "unauthorized",
+            Error::Unavailable(_) => "unavailable",
+            Error::Internal(_) => "internal",
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Error::Invalid { .. } | Error::Rejected(_) | Error::Malformed(_) | Error::Mismatch => {
+                StatusCode::BAD_REQUEST
+            }
+            Error::Duplicate => StatusCode::CONFLICT,
+            Error::NotFound => StatusCode::NOT_FOUND,
+            Error::Blocked => StatusCode::GONE,
+            Error::Unauthorized => StatusCode::UNAUTHORIZED,
+            Error::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            Error::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn title(&self) -> &'static str {
+        match self {
+            Error::Invalid { .. } => "invalid code",
+            Error::Rejected(_) => "destination rejected",
+            Error::Malformed(_) => "malformed request",
+            Error::Mismatch => "check character mismatch",
+            Error::Duplicate => "code already used",
+            Error::NotFound => "shrunk code not found",
+            Error::Blocked => "destination has been blocked",
+            Error::Unauthorized => "admin token required",
+            Error::Unavailable(_) => "service unavailable",
+            Error::Internal(_) => "internal error",
+        }
+    }
+
+    /// What the client is told beyond the title. Details of server-side
+    /// failures are only logged.
+    fn detail(&self) -> Option<String> {
+        match self {
+            Error::Invalid { field, failure } => Some(format!("{field} {failure}")),
+            Error::Rejected(reason) | Error::Malformed(reason) => Some(reason.clone()),
+            Error::Mismatch => Some("the code may have a typo".to_string()),
+            _ => None,
+        }
     }
 }
 
-impl Display for NotFound {
+impl Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "not found")
+        match self {
+            Error::Invalid { field, failure } => write!(f, "{field} {failure}"),
+            Error::Rejected(reason) => write!(f, "destination rejected: {reason}"),
+            Error::Malformed(reason) => write!(f, "malformed request: {reason}"),
+            Error::Unavailable(msg) => write!(f, "service unavailable: {msg}"),
+            Error::Internal(msg) => write!(f, "internal error: {msg}"),
+            _ => write!(f, "{}", self.title()),
+        }
     }
 }
 
-impl Display for Internal {
+impl Display for CaseConflicts {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "internal error: {}", self.0)
+        let groups: Vec<String> = self.0.iter().map(|codes| codes.join(" / ")).collect();
+        write!(f, "codes differing only by case: {}", groups.join(", "))
     }
 }
 
-impl Display for Storage {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Storage::Duplicate => NotFound.fmt(f),
-            Storage::Internal(msg) => write!(f, "internal storage error: {}", msg),
-            Storage::BadAlias => write!(f, "bad alias"),
-        }
+impl std::error::Error for Error {}
+impl std::error::Error for CaseConflicts {}
+
+impl From<crate::chain::Loop> for Error {
+    fn from(err: crate::chain::Loop) -> Self {
+        Error::Rejected(err.to_string())
     }
 }
 
-// TODO: Remove duplication (bad alias, internal error)
+impl From<crate::filter::Blocked> for Error {
+    fn from(blocked: crate::filter::Blocked) -> Self {
+        Error::Rejected(blocked.to_string())
+    }
+}
 
-impl Display for Load {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Load::NotFound => NotFound.fmt(f),
-            Load::Internal(msg) => write!(f, "internal load error: {}", msg),
-            Load::BadAlias => write!(f, "bad alias"),
-        }
+impl From<crate::policy::Violation> for Error {
+    fn from(violation: crate::policy::Violation) -> Self {
+        Error::Rejected(violation.to_string())
     }
 }
 
-impl Error for Duplicate {}
-impl Error for NotFound {}
-impl Error for Internal {}
-impl Error for Storage {}
-impl Error for Load {}
+impl From<JsonRejection> for Error {
+    fn from(rejection: JsonRejection) -> Self {
+        Error::Malformed(rejection.body_text())
+    }
+}
 
-impl From<Storage> for Internal {
-    fn from(err: Storage) -> Self {
-        match err {
-            Storage::Duplicate => Internal("duplicate entry".to_string()),
-            Storage::Internal(msg) => Internal(msg),
-            Storage::BadAlias => Internal("bad alias".to_string()),
-        }
+impl From<QueryRejection> for Error {
+    fn from(rejection: QueryRejection) -> Self {
+        Error::Malformed(rejection.body_text())
+    }
+}
+
+impl From<PathRejection> for Error {
+    fn from(rejection: PathRejection) -> Self {
+        Error::Malformed(rejection.body_text())
+    }
+}
+
+impl From<r2d2::Error> for Error {
+    fn from(err: r2d2::Error) -> Self {
+        Error::Unavailable(err.to_string())
     }
 }
 
-impl From<postgres::Error> for Storage {
+impl From<postgres::Error> for Error {
     fn from(err: postgres::Error) -> Self {
         match err.code().cloned() {
-            Some(postgres::error::SqlState::UNIQUE_VIOLATION) => Storage::Duplicate,
-            _ => Storage::Internal(err.to_string()),
+            Some(postgres::error::SqlState::UNIQUE_VIOLATION) => Error::Duplicate,
+            _ if err.is_closed() => Error::Unavailable(err.to_string()),
+            _ => Error::Internal(err.to_string()),
         }
     }
 }
 
-impl From<rusqlite::Error> for Storage {
+impl From<rusqlite::Error> for Error {
     fn from(err: rusqlite::Error) -> Self {
         match err.sqlite_error().map(|e| e.code) {
-            Some(rusqlite::ErrorCode::ConstraintViolation) => Storage::Duplicate,
-            _ => Storage::Internal(err.to_string()),
+            Some(rusqlite::ErrorCode::ConstraintViolation) => Error::Duplicate,
+            Some(rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked) => {
+                Error::Unavailable(err.to_string())
+            }
+            _ => Error::Internal(err.to_string()),
         }
     }
 }
 
-impl IntoResponse for Storage {
-    fn into_response(self) -> axum::http::Response<axum::body::Body> {
-        match self {
-            Storage::Duplicate => axum::http::Response::builder()
-                .status(axum::http::StatusCode::CONFLICT)
-                .body("code already used".into())
-                .unwrap(),
-            Storage::Internal(_) => axum::http::Response::builder()
-                .status(axum::http::StatusCode::INTERNAL_SERVER_ERROR)
-                .body("internal error".into())
-                .unwrap(),
-            Storage::BadAlias => axum::http::Response::builder()
-                .status(axum::http::StatusCode::INTERNAL_SERVER_ERROR)
-                .body("bad alias".into())
-                .unwrap(),
+impl Error {
+    /// The problem details sent to the client. Server-side failures are
+    /// logged, since the client isn't told about them.
+    pub fn report(&self) -> Problem {
+        if let Error::Unavailable(_) | Error::Internal(_) = self {
+            eprintln!("{self}");
+        }
+
+        let code = self.code();
+
+        Problem {
+            kind: format!("urn:shrink:problem:{code}"),
+            title: self.title(),
+            status: self.status().as_u16(),
+            code,
+            detail: self.detail(),
         }
     }
 }
 
-impl IntoResponse for Internal {
-    fn into_response(self) -> axum::http::Response<axum::body::Body> {
-        axum::http::Response::builder()
-            .status(axum::http::StatusCode::INTERNAL_SERVER_ERROR)
-            .body(self.0.into())
-            .unwrap()
+impl IntoResponse for Error {
+    fn into_response(self) -> axum::response::Response {
+        let mut response = (
+            self.status(),
+            [(header::CONTENT_TYPE, "application/problem+json")],
+            Json(self.report()),
+        )
+            .into_response();
+
+        if let Error::Unauthorized = self {
+            response.headers_mut().insert(
+                header::WWW_AUTHENTICATE,
+                header::HeaderValue::from_static("Bearer"),
+            );
+        }
+
+        response
     }
 }
 
-impl IntoResponse for Load {
-    fn into_response(self) -> axum::http::Response<axum::body::Body> {
-        match self {
-            Load::NotFound => axum::http::Response::builder()
-                .status(axum::http::StatusCode::NOT_FOUND)
-                .body("shrunk code not found".into())
-                .unwrap(),
-            Load::Internal(_) => axum::http::Response::builder()
-                .status(axum::http::StatusCode::INTERNAL_SERVER_ERROR)
-                .body("internal error".into())
-                .unwrap(),
-            Load::BadAlias => axum::http::Response::builder()
-                .status(axum::http::StatusCode::INTERNAL_SERVER_ERROR)
-                .body("bad alias".into())
-                .unwrap(),
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invalid() -> Error {
+        Error::Invalid {
+            field: "alias",
+            failure: Failure("alphanumeric".into()),
         }
     }
+
+    #[test]
+    fn statuses() {
+        assert_eq!(invalid().status(), StatusCode::BAD_REQUEST);
+        assert_eq!(Error::Duplicate.status(), StatusCode::CONFLICT);
+        assert_eq!(Error::NotFound.status(), StatusCode::NOT_FOUND);
+        assert_eq!(Error::Blocked.status(), StatusCode::GONE);
+        assert_eq!(Error::Unauthorized.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            Error::Unavailable("pool timed out".into()).status(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+    }
+
+    #[test]
+    fn details_are_not_leaked() {
+        let internal = Error::Internal("no such table: urls".into());
+
+        assert_eq!(internal.detail(), None);
+        assert_eq!(
+            invalid().detail(),
+            Some("alias must be alphanumeric".into())
+        );
+    }
 }
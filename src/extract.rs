@@ -0,0 +1,201 @@
+use std::marker::PhantomData;
+
+use axum::{
+    extract::{self, FromRequestParts},
+    http::{header, request::Parts},
+    response::{IntoResponse, Response},
+};
+use axum_macros::{FromRequest, FromRequestParts};
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::{
+    app::AppState,
+    error,
+    validator::{AllOf, Code, Validator},
+};
+
+/// `axum::Json`, rejecting malformed bodies with a problem.
+#[derive(FromRequest)]
+#[from_request(via(axum::Json), rejection(error::Error))]
+pub struct Json<T>(pub T);
+
+impl<T: Serialize> IntoResponse for Json<T> {
+    fn into_response(self) -> Response {
+        axum::Json(self.0).into_response()
+    }
+}
+
+/// `axum::extract::Query`, rejecting malformed queries with a problem.
+#[derive(FromRequestParts)]
+#[from_request(via(extract::Query), rejection(error::Error))]
+pub struct Query<T>(pub T);
+
+/// `axum::extract::Path`, rejecting malformed paths with a problem.
+#[derive(FromRequestParts)]
+#[from_request(via(extract::Path), rejection(error::Error))]
+pub struct Path<T>(pub T);
+
+/// Which of the app's validators a code is checked with.
+pub trait Rules {
+    /// What the code is called in error messages.
+    const FIELD: &'static str;
+
+    fn validator(state: &AppState) -> &Validator<AllOf>;
+}
+
+/// Generated codes and prefixes, checked with `CODE_RULES`.
+pub struct Codes;
+
+/// Custom aliases, checked with `ALIAS_RULES`.
+pub struct Aliases;
+
+/// Prefixes of generated codes, checked with `CODE_RULES` and `clean`.
+pub struct Prefixes;
+
+impl Rules for Codes {
+    const FIELD: &'static str = "code";
+
+    fn validator(state: &AppState) -> &Validator<AllOf> {
+        &state.validator
+    }
+}
+
+impl Rules for Aliases {
+    const FIELD: &'static str = "alias";
+
+    fn validator(state: &AppState) -> &Validator<AllOf> {
+        &state.aliases
+    }
+}
+
+impl Rules for Prefixes {
+    const FIELD: &'static str = "prefix";
+
+    fn validator(state: &AppState) -> &Validator<AllOf> {
+        &state.prefixes
+    }
+}
+
+/// A code taken from the request path that passed the validator picked by
+/// `R`.
+pub struct Valid<R = Codes> {
+    pub code: Code,
+    rules: PhantomData<R>,
+}
+
+/// Proof that a request carries the admin token as a bearer token. Without
+/// an admin token configured, every request is refused.
+pub struct Admin;
+
+/// Compares tokens in time independent of where they differ.
+fn same_token(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// A code from a request body, only usable once it's been checked.
+pub struct Unchecked<R = Codes>(String, PhantomData<R>);
+
+fn check<R: Rules>(state: &AppState, code: String) -> Result<Code, error::Error> {
+    R::validator(state)
+        .check(code)
+        .map_err(|failure| error::Error::Invalid {
+            field: R::FIELD,
+            failure,
+        })
+}
+
+impl<R: Rules> Unchecked<R> {
+    pub fn new(code: String) -> Self {
+        Self(code, PhantomData)
+    }
+
+    pub fn check(self, state: &AppState) -> Result<Code, error::Error> {
+        check::<R>(state, self.0)
+    }
+}
+
+impl<'de, R> Deserialize<'de> for Unchecked<R> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(|code| Self(code, PhantomData))
+    }
+}
+
+impl<R: Rules + Send> FromRequestParts<AppState> for Valid<R> {
+    type Rejection = error::Error;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let Path(code) = Path::<String>::from_request_parts(parts, state).await?;
+
+        check::<R>(state, code).map(|code| Self {
+            code,
+            rules: PhantomData,
+        })
+    }
+}
+
+impl FromRequestParts<AppState> for Admin {
+    type Rejection = error::Error;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let token = parts
+            .headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+
+        match (state.admin_token.as_deref(), token) {
+            (Some(expected), Some(token)) if same_token(expected.as_bytes(), token.as_bytes()) => {
+                Ok(Admin)
+            }
+            _ => Err(error::Error::Unauthorized),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::Body,
+        http::{Request, StatusCode},
+        routing::post,
+        Router,
+    };
+    use tower::ServiceExt;
+
+    use super::*;
+
+    async fn rejected(uri: &str, body: &'static str) -> (StatusCode, String) {
+        let router = Router::new().route(
+            "/{n}",
+            post(
+                |_: Path<u8>, _: Query<std::collections::HashMap<String, u8>>, _: Json<u8>| async {
+                },
+            ),
+        );
+        let request = Request::post(uri)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .unwrap();
+        let response = router.oneshot(request).await.unwrap();
+        let kind = response.headers()[header::CONTENT_TYPE].to_str().unwrap();
+
+        (response.status(), kind.to_string())
+    }
+
+    #[tokio::test]
+    async fn rejections_are_problems() {
+        for (uri, body) in [("/1", "{"), ("/1?n=x", "1"), ("/x", "1")] {
+            assert_eq!(
+                rejected(uri, body).await,
+                (StatusCode::BAD_REQUEST, "application/problem+json".into()),
+                "{uri} {body}"
+            );
+        }
+    }
+}
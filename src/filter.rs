@@ -0,0 +1,285 @@
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use url::Url;
+
+/// Destination filter driven by a domain blocklist and an optional allowlist.
+///
+/// Both files have one pattern per line, `#` starts a comment:
+///
+/// - `example.com` matches only that host,
+/// - `.example.com` matches the host and all of its subdomains,
+/// - `*.example.com` matches the subdomains only, `*` matches anything
+///   anywhere in the pattern (e.g. `login-*.example.com`).
+///
+/// When an allowlist is given, only hosts matching it can be shrunk.
+#[derive(Default)]
+pub struct DomainFilter {
+    lists: RwLock<Lists>,
+    blocklist: Option<PathBuf>,
+    allowlist: Option<PathBuf>,
+}
+
+#[derive(Default)]
+struct Lists {
+    block: Vec<Pattern>,
+    allow: Option<Vec<Pattern>>,
+    modified: Vec<Option<SystemTime>>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Pattern {
+    Exact(String),
+    Suffix(String),
+    Glob(String),
+}
+
+/// Why a destination didn't pass the filter.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Blocked {
+    Denied(String),
+    NotAllowed(String),
+}
+
+impl Display for Blocked {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Blocked::Denied(host) => write!(f, "domain `{host}` is blocked"),
+            Blocked::NotAllowed(host) => write!(f, "domain `{host}` is not allowed"),
+        }
+    }
+}
+
+impl std::error::Error for Blocked {}
+
+/// Whether `text` matches `pattern`, where `*` matches any run of characters.
+fn glob(pattern: &str, text: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == text,
+        Some((head, rest)) => {
+            let Some(text) = text.strip_prefix(head) else {
+                return false;
+            };
+
+            (0..=text.len())
+                .filter(|&i| text.is_char_boundary(i))
+                .any(|i| glob(rest, &text[i..]))
+        }
+    }
+}
+
+impl Pattern {
+    fn parse(line: &str) -> Option<Self> {
+        let line = line.split('#').next()?.trim().to_lowercase();
+
+        if line.is_empty() {
+            return None;
+        }
+
+        Some(match line.strip_prefix('.') {
+            Some(domain) => Pattern::Suffix(domain.to_string()),
+            None if line.contains('*') => Pattern::Glob(line),
+            None => Pattern::Exact(line),
+        })
+    }
+
+    fn matches(&self, host: &str) -> bool {
+        match self {
+            Pattern::Exact(domain) => host == domain,
+            Pattern::Suffix(domain) => {
+                host == domain
+                    || host
+                        .strip_suffix(domain.as_str())
+                        .is_some_and(|sub| sub.ends_with('.'))
+            }
+            Pattern::Glob(pattern) => glob(pattern, host),
+        }
+    }
+}
+
+fn parse(patterns: &str) -> Vec<Pattern> {
+    patterns.lines().filter_map(Pattern::parse).collect()
+}
+
+fn modified(path: &Option<PathBuf>) -> Option<SystemTime> {
+    std::fs::metadata(path.as_ref()?).ok()?.modified().ok()
+}
+
+impl DomainFilter {
+    pub fn new(block: &str, allow: Option<&str>) -> Self {
+        Self {
+            lists: RwLock::new(Lists {
+                block: parse(block),
+                allow: allow.map(parse),
+                modified: Vec::new(),
+            }),
+            ..Self::default()
+        }
+    }
+
+    pub fn from_files(
+        blocklist: Option<PathBuf>,
+        allowlist: Option<PathBuf>,
+    ) -> Result<Self, std::io::Error> {
+        let filter = Self {
+            blocklist,
+            allowlist,
+            ..Self::default()
+        };
+
+        filter.reload()?;
+
+        Ok(filter)
+    }
+
+    /// Reads the files again if they changed since the last time.
+    pub fn reload(&self) -> Result<bool, std::io::Error> {
+        let modified = vec![modified(&self.blocklist), modified(&self.allowlist)];
+
+        if self.lists.read().unwrap().modified == modified {
+            return Ok(false);
+        }
+
+        let read = |path: &Option<PathBuf>| -> Result<Option<Vec<Pattern>>, std::io::Error> {
+            path.as_ref()
+                .map(|path| std::fs::read_to_string(path).map(|list| parse(&list)))
+                .transpose()
+        };
+
+        let lists = Lists {
+            block: read(&self.blocklist)?.unwrap_or_default(),
+            allow: read(&self.allowlist)?,
+            modified,
+        };
+
+        *self.lists.write().unwrap() = lists;
+
+        Ok(true)
+    }
+
+    /// Reloads the lists in the background whenever the files change.
+    pub fn watch(self: &Arc<Self>, interval: Duration) {
+        let filter = self.clone();
+
+        thread::spawn(move || loop {
+            thread::sleep(interval);
+
+            if let Err(e) = filter.reload() {
+                eprintln!("Failed to reload domain lists: {e}");
+            }
+        });
+    }
+
+    pub fn check(&self, url: &Url) -> Result<(), Blocked> {
+        let Some(host) = url.host_str() else {
+            return Ok(());
+        };
+
+        let host = host.trim_end_matches('.').to_lowercase();
+        let lists = self.lists.read().unwrap();
+
+        if lists.block.iter().any(|pattern| pattern.matches(&host)) {
+            return Err(Blocked::Denied(host));
+        }
+
+        match &lists.allow {
+            Some(allow) if !allow.iter().any(|pattern| pattern.matches(&host)) => {
+                Err(Blocked::NotAllowed(host))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(filter: &DomainFilter, url: &str) -> Result<(), Blocked> {
+        filter.check(&url.parse().unwrap())
+    }
+
+    #[test]
+    fn exact_match() {
+        let filter = DomainFilter::new("evil.com", None);
+
+        assert!(check(&filter, "https://evil.com/login").is_err());
+        assert!(check(&filter, "https://www.evil.com/").is_ok());
+        assert!(check(&filter, "https://notevil.com/").is_ok());
+    }
+
+    #[test]
+    fn suffix_match() {
+        let filter = DomainFilter::new(".evil.com", None);
+
+        assert!(check(&filter, "https://evil.com/").is_err());
+        assert!(check(&filter, "https://a.b.evil.com/").is_err());
+        assert!(check(&filter, "https://notevil.com/").is_ok());
+    }
+
+    #[test]
+    fn wildcard_match() {
+        let filter = DomainFilter::new("*.evil.com\nlogin-*.bank.com", None);
+
+        assert!(check(&filter, "https://evil.com/").is_ok());
+        assert!(check(&filter, "https://www.evil.com/").is_err());
+        assert!(check(&filter, "https://login-secure.bank.com/").is_err());
+        assert!(check(&filter, "https://bank.com/").is_ok());
+    }
+
+    #[test]
+    fn comments_and_case_are_ignored() {
+        let filter = DomainFilter::new("# phishing\nEvil.COM # reported", None);
+
+        assert_eq!(
+            check(&filter, "https://EVIL.com./"),
+            Err(Blocked::Denied("evil.com".into()))
+        );
+    }
+
+    #[test]
+    fn allowlist_restricts() {
+        let filter = DomainFilter::new("", Some(".company.com"));
+
+        assert!(check(&filter, "https://wiki.company.com/").is_ok());
+        assert_eq!(
+            check(&filter, "https://example.com/"),
+            Err(Blocked::NotAllowed("example.com".into()))
+        );
+    }
+
+    #[test]
+    fn blocklist_wins_over_allowlist() {
+        let filter = DomainFilter::new("old.company.com", Some(".company.com"));
+
+        assert!(check(&filter, "https://old.company.com/").is_err());
+    }
+
+    #[test]
+    fn reloads_changed_files() {
+        let path = std::env::temp_dir().join(format!("shrink-blocklist-{}", std::process::id()));
+        std::fs::write(&path, "evil.com").unwrap();
+
+        let filter = DomainFilter::from_files(Some(path.clone()), None).unwrap();
+        assert!(check(&filter, "https://evil.com/").is_err());
+
+        std::fs::write(&path, "worse.com\n").unwrap();
+        // Make sure the modification time changes even on coarse filesystems.
+        let later = SystemTime::now() + Duration::from_secs(5);
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+
+        assert!(filter.reload().unwrap());
+        assert!(check(&filter, "https://evil.com/").is_ok());
+        assert!(check(&filter, "https://worse.com/").is_err());
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,201 @@
+use crate::validator::Validate;
+
+/// Characters a generator draws codes from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Alphabet(&'static [u8]);
+
+/// Characters people mix up when reading codes aloud or off a printout, and
+/// the one we keep for each group.
+const CONFUSABLES: &[(char, char)] = &[('O', '0'), ('o', '0'), ('I', '1'), ('l', '1')];
+
+impl Alphabet {
+    pub const BASE62: Alphabet =
+        Alphabet(b"0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ");
+
+    /// `BASE62` without `O`, `o`, `I` and `l`, which are easily confused with
+    /// `0` and `1`.
+    pub const UNAMBIGUOUS: Alphabet =
+        Alphabet(b"0123456789abcdefghijkmnpqrstuvwxyzABCDEFGHJKLMNPQRSTUVWXYZ");
+
+    pub fn chars(&self) -> &'static [u8] {
+        self.0
+    }
+
+    fn index(&self, c: char) -> Option<usize> {
+        self.0.iter().position(|&b| b as char == c)
+    }
+
+    /// Replaces confusable characters that are not part of the alphabet with
+    /// the one we keep, e.g. `O` becomes `0`.
+    pub fn normalize(&self, code: &str) -> String {
+        code.chars()
+            .map(|c| match CONFUSABLES.iter().find(|(from, _)| *from == c) {
+                Some(&(_, to)) if self.index(c).is_none() && self.index(to).is_some() => to,
+                _ => c,
+            })
+            .collect()
+    }
+
+    /// Check character for `code` using the Luhn mod N algorithm, which
+    /// catches every single character typo and most adjacent swaps.
+    pub fn check_char(&self, code: &str) -> Option<char> {
+        let n = self.0.len();
+        let sum = self.luhn_sum(code, 2)?;
+
+        Some(self.0[(n - sum % n) % n] as char)
+    }
+
+    /// Whether the last character of `code` is the check character of the rest.
+    pub fn verify(&self, code: &str) -> bool {
+        !code.is_empty()
+            && self
+                .luhn_sum(code, 1)
+                .is_some_and(|sum| sum % self.0.len() == 0)
+    }
+
+    fn luhn_sum(&self, code: &str, first_factor: usize) -> Option<usize> {
+        let n = self.0.len();
+        let mut factor = first_factor;
+        let mut sum = 0;
+
+        for c in code.chars().rev() {
+            let addend = factor * self.index(c)?;
+            sum += addend / n + addend % n;
+            factor = 3 - factor;
+        }
+
+        Some(sum)
+    }
+
+    /// Codes that differ from `code` by one substituted character or one
+    /// adjacent swap and carry a valid check character.
+    pub fn corrections(&self, code: &str) -> Vec<String> {
+        let chars: Vec<char> = code.chars().collect();
+        let mut candidates = Vec::new();
+
+        for i in 0..chars.len() {
+            for &b in self.0 {
+                if b as char != chars[i] {
+                    let mut candidate = chars.clone();
+                    candidate[i] = b as char;
+                    candidates.push(candidate.into_iter().collect::<String>());
+                }
+            }
+
+            if i + 1 < chars.len() && chars[i] != chars[i + 1] {
+                let mut candidate = chars.clone();
+                candidate.swap(i, i + 1);
+                candidates.push(candidate.into_iter().collect());
+            }
+        }
+
+        candidates.retain(|candidate| self.verify(candidate));
+        candidates
+    }
+}
+
+/// Codes can only get a check character if all of their characters are in
+/// the alphabet, prefixes included.
+impl Validate for Alphabet {
+    fn validate(&self, code: &str) -> bool {
+        code.chars().all(|c| self.index(c).is_some())
+    }
+
+    fn describe(&self) -> String {
+        "made of the characters of generated codes".into()
+    }
+}
+
+/// How generated codes end in a check character: the alphabet they're drawn
+/// from and their length, check character included.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Checksum {
+    pub alphabet: Alphabet,
+    pub length: usize,
+}
+
+impl Checksum {
+    /// Whether `code`, once normalized, could have been generated, so it's
+    /// worth correcting. Aliases and prefixed codes are only looked up as
+    /// they are.
+    pub fn covers(&self, normalized: &str) -> bool {
+        normalized.chars().count() == self.length && self.alphabet.validate(normalized)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checked(code: &str) -> String {
+        let check = Alphabet::UNAMBIGUOUS.check_char(code).unwrap();
+        format!("{code}{check}")
+    }
+
+    #[test]
+    fn unambiguous_has_no_confusables() {
+        let chars = Alphabet::UNAMBIGUOUS.chars();
+
+        assert!(CONFUSABLES
+            .iter()
+            .all(|(from, _)| !chars.contains(&(*from as u8))));
+    }
+
+    #[test]
+    fn check_char_verifies() {
+        assert!(Alphabet::UNAMBIGUOUS.verify(&checked("hWU7Xgc")));
+        assert!(Alphabet::BASE62.verify(&format!(
+            "abc{}",
+            Alphabet::BASE62.check_char("abc").unwrap()
+        )));
+    }
+
+    #[test]
+    fn typo_fails_verification() {
+        let mut code = checked("hWU7Xgc");
+        code.replace_range(2..3, "V");
+
+        assert!(!Alphabet::UNAMBIGUOUS.verify(&code));
+    }
+
+    #[test]
+    fn swap_fails_verification() {
+        let code = checked("hWU7Xgc");
+        let swapped = format!("{}{}{}", &code[..1], "UW", &code[3..]);
+
+        assert!(!Alphabet::UNAMBIGUOUS.verify(&swapped));
+    }
+
+    #[test]
+    fn normalizes_confusables() {
+        assert_eq!(Alphabet::UNAMBIGUOUS.normalize("OolI"), "0011");
+        assert_eq!(Alphabet::BASE62.normalize("OolI"), "OolI");
+    }
+
+    #[test]
+    fn corrections_contain_original() {
+        let code = checked("hWU7Xgc");
+        let mut typo = code.clone();
+        typo.replace_range(0..1, "k");
+
+        assert!(Alphabet::UNAMBIGUOUS.corrections(&typo).contains(&code));
+    }
+
+    #[test]
+    fn foreign_characters_do_not_verify() {
+        assert!(!Alphabet::UNAMBIGUOUS.verify("abc-"));
+        assert_eq!(Alphabet::UNAMBIGUOUS.check_char("l"), None);
+    }
+
+    #[test]
+    fn covers_codes_of_the_generated_length() {
+        let checksum = Checksum {
+            alphabet: Alphabet::UNAMBIGUOUS,
+            length: 8,
+        };
+
+        assert!(checksum.covers(&checked("hWU7Xgc")));
+        assert!(!checksum.covers("blaze"));
+        assert!(!checksum.covers("blaze-it"));
+    }
+}
@@ -0,0 +1,29 @@
+use url::Url;
+
+/// What a `Generator` knows about the request it generates a code for.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Context<'a> {
+    /// The URL being shrunk, `None` when codes are generated ahead of time.
+    pub url: Option<&'a Url>,
+    /// Prefix the client asked the code to start with.
+    pub prefix: Option<&'a str>,
+    /// Tenant the link is created for.
+    pub tenant: Option<&'a str>,
+}
+
+impl<'a> Context<'a> {
+    pub fn new(url: &'a Url) -> Self {
+        Self {
+            url: Some(url),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_prefix(self, prefix: Option<&'a str>) -> Self {
+        Self { prefix, ..self }
+    }
+
+    pub fn with_tenant(self, tenant: Option<&'a str>) -> Self {
+        Self { tenant, ..self }
+    }
+}
@@ -1,11 +1,15 @@
-use crate::{validator::Code, Generator};
+use std::sync::atomic::{AtomicUsize, Ordering};
 
-#[derive(Clone, Default)]
-pub struct Counter(usize); // XXX: This is not `Send` (I think).
+use crate::{error, validator::Code, Generator};
+
+use super::Context;
+
+#[derive(Default)]
+pub struct Counter(AtomicUsize);
 
 impl Generator for Counter {
-    fn generate(&mut self, _: &url::Url) -> Code {
-        self.0 += 1;
-        Code::new(self.0.to_string())
+    fn generate(&self, _: &Context) -> Result<Code, error::Error> {
+        let n = self.0.fetch_add(1, Ordering::Relaxed) + 1;
+        Ok(Code::new(n.to_string()))
     }
 }
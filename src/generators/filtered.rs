@@ -0,0 +1,95 @@
+use std::sync::Arc;
+
+use crate::{
+    error,
+    validator::{Code, Validate},
+    Generator,
+};
+
+use super::Context;
+
+/// How many codes are generated before giving up on one the filter accepts.
+const MAX_ATTEMPTS: usize = 100;
+
+/// Regenerates codes until the filter accepts them, e.g. to keep offensive
+/// words out of random codes.
+pub struct Filtered<G, V> {
+    codes: G,
+    filter: Arc<V>,
+}
+
+impl<G, V> Filtered<G, V> {
+    pub fn new(codes: G, filter: Arc<V>) -> Self {
+        Self { codes, filter }
+    }
+
+    pub fn inner(&self) -> &G {
+        &self.codes
+    }
+}
+
+impl<G: Generator, V: Validate + Send + Sync> Generator for Filtered<G, V> {
+    fn generate(&self, context: &Context) -> Result<Code, error::Error> {
+        self.generate_unique(context).map(|(code, _)| code)
+    }
+
+    fn generate_unique(&self, context: &Context) -> Result<(Code, bool), error::Error> {
+        for _ in 0..MAX_ATTEMPTS {
+            let (code, unique) = self.codes.generate_unique(context)?;
+
+            if self.filter.validate(code.as_str()) {
+                return Ok((code, unique));
+            }
+        }
+
+        Err(error::Error::Unavailable(
+            "no generated code passed the filter".into(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        generators::{Counter, Words},
+        validator::{Alnum, Profanity},
+    };
+
+    struct Nothing;
+
+    impl Validate for Nothing {
+        fn validate(&self, _: &str) -> bool {
+            false
+        }
+
+        fn describe(&self) -> String {
+            "nothing".into()
+        }
+    }
+
+    #[test]
+    fn gives_up_when_everything_is_rejected() {
+        let codes = Filtered::new(Counter::default(), Arc::new(Nothing));
+
+        assert!(codes.generate(&Context::default()).is_err());
+    }
+
+    #[test]
+    fn passes_accepted_codes() {
+        let codes = Filtered::new(Counter::default(), Arc::new(Alnum));
+
+        assert_eq!(codes.generate(&Context::default()).unwrap().as_str(), "1");
+    }
+
+    #[test]
+    fn word_codes_are_filtered() {
+        let profanity = Arc::new(Profanity::default());
+        let codes = Filtered::new(Words::default(), profanity.clone());
+
+        for _ in 0..1000 {
+            let code = codes.generate(&Context::default()).unwrap();
+            assert!(profanity.validate(code.as_str()), "{}", code.as_str());
+        }
+    }
+}
@@ -1,5 +1,15 @@
+mod alphabet;
+mod context;
 mod counter;
+mod filtered;
+mod pool;
 mod rb62;
+mod words;
 
+pub use alphabet::{Alphabet, Checksum};
+pub use context::Context;
 pub use counter::Counter;
+pub use filtered::Filtered;
+pub use pool::KeyPool;
 pub use rb62::RB62;
+pub use words::Words;
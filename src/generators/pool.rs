@@ -0,0 +1,230 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::{error, validator::Code, Generator, Reserve};
+
+use super::Context;
+
+/// Hands out codes that were generated and reserved in storage ahead of time,
+/// so `App::shrink` doesn't have to check every new code for a collision.
+///
+/// A background thread refills the queue up to the high-water mark once it
+/// drops to the low-water mark. Reserved codes are never reserved again, so
+/// the ones left in the queue on shutdown are simply lost.
+///
+/// Pooled codes are generated without a `Context`, so codes for a requested
+/// prefix or tenant are generated inline instead, and aren't unique. So are
+/// codes needed while the queue is empty, e.g. because storage is down:
+/// requests hold the app's lock on an async worker, they can't wait for a
+/// refill.
+pub struct KeyPool<G> {
+    shared: Arc<Shared<G>>,
+    pooled: bool,
+}
+
+struct Shared<G> {
+    codes: G,
+    queue: Mutex<VecDeque<Code>>,
+    /// Signalled when the queue drops to the low-water mark.
+    drained: Condvar,
+    low: usize,
+    high: usize,
+}
+
+impl<G: Generator> Shared<G> {
+    fn refill(&self, storage: &mut impl Reserve) {
+        loop {
+            let missing = {
+                let queue = self.queue.lock().unwrap();
+                let queue = self
+                    .drained
+                    .wait_while(queue, |queue| queue.len() > self.low)
+                    .unwrap();
+
+                self.high - queue.len()
+            };
+
+            let batch = (0..missing)
+                .filter_map(|_| self.codes.generate(&Context::default()).ok())
+                .collect();
+
+            match storage.reserve(batch) {
+                Ok(reserved) => self.queue.lock().unwrap().extend(reserved),
+                Err(e) => {
+                    eprintln!("Failed to reserve codes: {e}");
+                    thread::sleep(Duration::from_secs(1));
+                }
+            }
+        }
+    }
+}
+
+impl<G: Generator> KeyPool<G> {
+    /// No pool at all, every code comes straight from `codes`.
+    pub fn inline(codes: G) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                codes,
+                queue: Mutex::new(VecDeque::new()),
+                drained: Condvar::new(),
+                low: 0,
+                high: 0,
+            }),
+            pooled: false,
+        }
+    }
+
+    /// Starts the background thread reserving codes from `codes` in `storage`.
+    pub fn spawn<R>(codes: G, mut storage: R, low: usize, high: usize) -> Self
+    where
+        G: 'static,
+        R: Reserve + Send + 'static,
+    {
+        let shared = Arc::new(Shared {
+            codes,
+            queue: Mutex::new(VecDeque::with_capacity(high)),
+            drained: Condvar::new(),
+            low: low.min(high.saturating_sub(1)),
+            high: high.max(1),
+        });
+
+        let refiller = shared.clone();
+        thread::spawn(move || refiller.refill(&mut storage));
+
+        Self {
+            shared,
+            pooled: true,
+        }
+    }
+}
+
+impl<G: Generator> Generator for KeyPool<G> {
+    fn generate(&self, context: &Context) -> Result<Code, error::Error> {
+        self.generate_unique(context).map(|(code, _)| code)
+    }
+
+    fn generate_unique(&self, context: &Context) -> Result<(Code, bool), error::Error> {
+        let inline = || {
+            self.shared
+                .codes
+                .generate(context)
+                .map(|code| (code, false))
+        };
+
+        if !self.pooled || context.prefix.is_some() || context.tenant.is_some() {
+            return inline();
+        }
+
+        let mut queue = self.shared.queue.lock().unwrap();
+        let code = queue.pop_front();
+
+        if queue.len() <= self.shared.low {
+            self.shared.drained.notify_one();
+        }
+        drop(queue);
+
+        match code {
+            Some(code) => Ok((code, true)),
+            None => inline(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::{
+        error,
+        generators::{Counter, RB62},
+    };
+
+    #[derive(Clone, Default)]
+    struct Reserved(Arc<Mutex<HashSet<Code>>>);
+
+    impl Reserve for Reserved {
+        fn reserve(&mut self, codes: Vec<Code>) -> Result<Vec<Code>, error::Error> {
+            let mut reserved = self.0.lock().unwrap();
+            Ok(codes
+                .into_iter()
+                .filter(|c| reserved.insert(c.clone()))
+                .collect())
+        }
+    }
+
+    /// The next `count` codes handed out from the queue, skipping the ones
+    /// generated inline while it's refilled.
+    fn pooled(pool: &KeyPool<Counter>, count: usize) -> Vec<Code> {
+        let mut codes = Vec::new();
+
+        while codes.len() < count {
+            match pool.generate_unique(&Context::default()).unwrap() {
+                (code, true) => codes.push(code),
+                (_, false) => thread::yield_now(),
+            }
+        }
+
+        codes
+    }
+
+    #[test]
+    fn hands_out_unique_codes() {
+        let pool = KeyPool::spawn(Counter::default(), Reserved::default(), 2, 8);
+
+        let codes: HashSet<Code> = pooled(&pool, 100).into_iter().collect();
+
+        assert_eq!(codes.len(), 100);
+    }
+
+    #[test]
+    fn skips_codes_reserved_before_a_restart() {
+        let reserved = Reserved::default();
+
+        let before = KeyPool::spawn(Counter::default(), reserved.clone(), 0, 4);
+        let first = pooled(&before, 1).remove(0);
+
+        let after = KeyPool::spawn(Counter::default(), reserved, 0, 4);
+        let codes = pooled(&after, 10);
+
+        assert!(!codes.contains(&first));
+    }
+
+    #[test]
+    fn prefixed_codes_are_generated_inline() {
+        let pool = KeyPool::spawn(RB62::default(), Reserved::default(), 0, 4);
+        let (code, unique) = pool
+            .generate_unique(&Context::default().with_prefix(Some("team")))
+            .unwrap();
+
+        assert!(code.as_str().starts_with("team"));
+        assert!(!unique);
+    }
+
+    struct Down;
+
+    impl Reserve for Down {
+        fn reserve(&mut self, _: Vec<Code>) -> Result<Vec<Code>, error::Error> {
+            Err(error::Error::Unavailable("down".into()))
+        }
+    }
+
+    #[test]
+    fn generates_inline_while_storage_is_down() {
+        let pool = KeyPool::spawn(Counter::default(), Down, 0, 4);
+
+        let (_, unique) = pool.generate_unique(&Context::default()).unwrap();
+        assert!(!unique);
+    }
+
+    #[test]
+    fn inline_is_not_unique() {
+        let pool = KeyPool::inline(Counter::default());
+
+        let (code, unique) = pool.generate_unique(&Context::default()).unwrap();
+        assert_eq!(code.as_str(), "1");
+        assert!(!unique);
+    }
+}
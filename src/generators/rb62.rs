@@ -1,23 +1,84 @@
 use rand::Rng;
 
-use crate::{validator::Code, Generator};
+use crate::{
+    error,
+    validator::{Code, Failure, Validate},
+    Generator,
+};
 
-const CHARS: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+use super::{Alphabet, Checksum, Context};
 
 /// Random Base62 generator.
-#[derive(Default)]
-pub struct RB62;
+///
+/// Can be switched to the confusion-free `Alphabet::UNAMBIGUOUS` and made to
+/// append a check character, so typos can be caught on redirect. A requested
+/// prefix is put in front of the random part.
+pub struct RB62 {
+    alphabet: Alphabet,
+    length: usize,
+    check: bool,
+}
+
+impl RB62 {
+    pub fn unambiguous() -> Self {
+        Self {
+            alphabet: Alphabet::UNAMBIGUOUS,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_check(self) -> Self {
+        Self {
+            check: true,
+            ..self
+        }
+    }
+
+    pub fn alphabet(&self) -> Alphabet {
+        self.alphabet
+    }
+
+    /// How the trailing check character is made, if there is one.
+    pub fn checksum(&self) -> Option<Checksum> {
+        self.check.then_some(Checksum {
+            alphabet: self.alphabet,
+            length: self.length + 1,
+        })
+    }
+}
+
+impl Default for RB62 {
+    fn default() -> Self {
+        Self {
+            alphabet: Alphabet::BASE62,
+            length: 7,
+            check: false,
+        }
+    }
+}
 
 impl Generator for RB62 {
-    fn generate(&mut self, _: &url::Url) -> Code {
-        // Thought of reusing the random nubmer generator (`rng`) by putting
-        // storing it in the struct, but that would make the struct not `Send`.
+    fn generate(&self, context: &Context) -> Result<Code, error::Error> {
+        // The thread-local generator is cheap to get, storing one in the
+        // struct would need a `Mutex` to keep it `Sync`.
         let mut rng = rand::rng();
+        let chars = self.alphabet.chars();
+
+        let mut code = context.prefix.unwrap_or_default().to_string();
+        code.extend((0..self.length).map(|_| chars[rng.random_range(0..chars.len())] as char));
 
-        let code = (0..7)
-            .map(|_| CHARS[rng.random_range(0..CHARS.len())] as char)
-            .collect();
+        if self.check {
+            // Prefixes outside the alphabet would leave the code without one.
+            let check = self
+                .alphabet
+                .check_char(&code)
+                .ok_or_else(|| error::Error::Invalid {
+                    field: "prefix",
+                    failure: Failure(self.alphabet.describe()),
+                })?;
+            code.push(check);
+        }
 
-        Code::new(code)
+        Ok(Code::new(code))
     }
 }
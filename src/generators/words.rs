@@ -0,0 +1,125 @@
+use rand::{seq::IndexedRandom, Rng};
+
+use crate::{error, validator::Code, Generator};
+
+use super::Context;
+
+const ADJECTIVES: &str = include_str!("words/adjectives.txt");
+const NOUNS: &str = include_str!("words/nouns.txt");
+
+/// Human-readable generator producing codes like `brave-otter-42`.
+///
+/// All but the last word are adjectives, the last one is a noun, and a two
+/// digit number is appended to widen the code space. A requested prefix
+/// becomes the first word.
+pub struct Words {
+    adjectives: Vec<&'static str>,
+    nouns: Vec<&'static str>,
+    count: usize,
+    separator: char,
+}
+
+impl Words {
+    pub fn new(count: usize, separator: char) -> Self {
+        Self {
+            adjectives: ADJECTIVES.lines().collect(),
+            nouns: NOUNS.lines().collect(),
+            count: count.max(1),
+            separator,
+        }
+    }
+
+    pub fn separator(&self) -> char {
+        self.separator
+    }
+}
+
+impl Default for Words {
+    fn default() -> Self {
+        Self::new(2, '-')
+    }
+}
+
+impl Generator for Words {
+    fn generate(&self, context: &Context) -> Result<Code, error::Error> {
+        let mut rng = rand::rng();
+
+        let mut parts: Vec<String> = context.prefix.map(str::to_string).into_iter().collect();
+
+        parts.extend(
+            (1..self.count)
+                .filter_map(|_| self.adjectives.choose(&mut rng))
+                .map(|word| word.to_string()),
+        );
+
+        parts.extend(self.nouns.choose(&mut rng).map(|word| word.to_string()));
+        parts.push(rng.random_range(0..100).to_string());
+
+        Ok(Code::new(parts.join(&self.separator.to_string())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validator::Validate;
+
+    fn generate(words: &Words) -> String {
+        let url = "https://blazinglyfast.net/".parse().unwrap();
+        words
+            .generate(&Context::new(&url))
+            .unwrap()
+            .as_str()
+            .to_string()
+    }
+
+    #[test]
+    fn default_has_two_words_and_a_number() {
+        let code = generate(&Words::default());
+        let parts: Vec<&str> = code.split('-').collect();
+
+        assert_eq!(parts.len(), 3);
+        assert!(parts[2].parse::<u8>().is_ok());
+    }
+
+    #[test]
+    fn respects_count_and_separator() {
+        let code = generate(&Words::new(4, '_'));
+
+        assert_eq!(code.split('_').count(), 5);
+        assert!(!code.contains('-'));
+    }
+
+    #[test]
+    fn prefix_is_the_first_word() {
+        let url = "https://blazinglyfast.net/".parse().unwrap();
+        let context = Context::new(&url).with_prefix(Some("team"));
+        let code = Words::default().generate(&context).unwrap();
+
+        assert!(code.as_str().starts_with("team-"));
+        assert_eq!(code.as_str().split('-').count(), 4);
+    }
+
+    #[test]
+    fn wordlists_are_clean() {
+        let words = Words::default();
+        let profanity = crate::validator::Profanity::default();
+
+        assert!(words
+            .adjectives
+            .iter()
+            .chain(&words.nouns)
+            .all(|word| profanity.validate(word)));
+    }
+
+    #[test]
+    fn wordlists_are_alphabetic() {
+        let words = Words::default();
+
+        assert!(words
+            .adjectives
+            .iter()
+            .chain(&words.nouns)
+            .all(|word| word.chars().all(|c| c.is_ascii_lowercase())));
+    }
+}
@@ -1,24 +1,128 @@
 pub mod app;
+pub mod canonical;
+pub mod chain;
 pub mod error;
+pub mod extract;
+pub mod filter;
 pub mod generators;
+pub mod link;
+pub mod listing;
+pub mod lookup;
+pub mod policy;
+pub mod preview;
 pub mod storage;
+pub mod suggest;
+pub mod transfer;
 pub mod validator;
 
+use generators::Context;
+use link::Link;
 use url::Url;
 use validator::Code;
 
 // NOTE: Maybe consider going all async?
 
 pub trait Shrinker {
-    fn shrink(&mut self, url: Url) -> Result<Code, error::Internal>;
-    fn expand(&self, code: &Code) -> Result<Url, error::Load>;
+    fn shrink(&mut self, url: Url) -> Result<Code, error::Error>;
+    fn expand(&self, code: &Code) -> Result<Url, error::Error>;
 }
 
-trait Generator {
-    fn generate(&mut self, url: &Url) -> Code;
+/// Source of codes for shrunk URLs.
+///
+/// Generators are shared between requests, so any state they keep has to be
+/// behind interior mutability.
+pub trait Generator: Send + Sync {
+    /// A new code, or an error if none could be made for `context`.
+    fn generate(&self, context: &Context) -> Result<Code, error::Error>;
+
+    /// Like `generate`, also telling whether the code is known to be unused,
+    /// so it doesn't have to be checked against storage.
+    fn generate_unique(&self, context: &Context) -> Result<(Code, bool), error::Error> {
+        self.generate(context).map(|code| (code, false))
+    }
+}
+
+impl<G: Generator + ?Sized> Generator for Box<G> {
+    fn generate(&self, context: &Context) -> Result<Code, error::Error> {
+        (**self).generate(context)
+    }
+
+    fn generate_unique(&self, context: &Context) -> Result<(Code, bool), error::Error> {
+        (**self).generate_unique(context)
+    }
 }
 
 pub trait Storage {
-    fn store(&mut self, url: Url, code: &Code) -> Result<(), error::Storage>;
-    fn load(&self, code: &Code) -> Result<Url, error::Load>;
+    fn store(&mut self, link: Link, code: &Code) -> Result<(), error::Error>;
+    fn load(&self, code: &Code) -> Result<Link, error::Error>;
+
+    /// Stores a link whether or not its code is used, replacing the link
+    /// stored under it.
+    fn replace(&mut self, link: Link, code: &Code) -> Result<(), error::Error>;
+
+    /// Stores many links at once, with a result for each in the same order.
+    fn store_many(&mut self, links: Vec<(Link, Code)>) -> Vec<Result<(), error::Error>> {
+        links
+            .into_iter()
+            .map(|(link, code)| self.store(link, &code))
+            .collect()
+    }
+
+    /// Loads many links at once, with a result for each code in the same
+    /// order.
+    fn load_many(&self, codes: &[Code]) -> Vec<Result<Link, error::Error>> {
+        codes.iter().map(|code| self.load(code)).collect()
+    }
+
+    /// A page of the stored links matching `query`.
+    fn list(&self, query: &listing::Query) -> Result<listing::Page, error::Error>;
+
+    /// Links pointing at `target`, ordered by code except on Redis.
+    fn find(&self, target: &lookup::Target) -> Result<Vec<(Code, Link)>, error::Error>;
+
+    /// Moves links on domain `from` and its subdomains to `to`, both
+    /// normalized with `lookup::normalize_host`. Returns the links as
+    /// they're stored now.
+    fn retarget(&mut self, from: &str, to: &str) -> Result<Vec<(Code, Link)>, error::Error>;
+}
+
+impl<S: Storage + ?Sized> Storage for Box<S> {
+    fn store(&mut self, link: Link, code: &Code) -> Result<(), error::Error> {
+        (**self).store(link, code)
+    }
+
+    fn load(&self, code: &Code) -> Result<Link, error::Error> {
+        (**self).load(code)
+    }
+
+    fn replace(&mut self, link: Link, code: &Code) -> Result<(), error::Error> {
+        (**self).replace(link, code)
+    }
+
+    fn store_many(&mut self, links: Vec<(Link, Code)>) -> Vec<Result<(), error::Error>> {
+        (**self).store_many(links)
+    }
+
+    fn load_many(&self, codes: &[Code]) -> Vec<Result<Link, error::Error>> {
+        (**self).load_many(codes)
+    }
+
+    fn list(&self, query: &listing::Query) -> Result<listing::Page, error::Error> {
+        (**self).list(query)
+    }
+
+    fn find(&self, target: &lookup::Target) -> Result<Vec<(Code, Link)>, error::Error> {
+        (**self).find(target)
+    }
+
+    fn retarget(&mut self, from: &str, to: &str) -> Result<Vec<(Code, Link)>, error::Error> {
+        (**self).retarget(from, to)
+    }
+}
+
+/// Storage that can set codes aside, so that nothing else hands them out.
+pub trait Reserve {
+    /// Reserves the `codes` that are neither used nor reserved yet, and
+    /// returns those.
+    fn reserve(&mut self, codes: Vec<Code>) -> Result<Vec<Code>, error::Error>;
 }
@@ -0,0 +1,125 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+/// A shrunk link: where it leads and what's known about it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Link {
+    pub url: Url,
+    /// Unix timestamps in seconds, 0 for links stored before these were
+    /// kept.
+    #[serde(default)]
+    pub created_at: i64,
+    #[serde(default)]
+    pub updated_at: i64,
+    #[serde(flatten)]
+    pub meta: Meta,
+}
+
+/// What clients can tell about a link when creating it.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Meta {
+    /// Who created the link, as the client says. It isn't verified, so
+    /// it's only a label for admins to filter by.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Only shown to admins, like `owner`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+}
+
+/// The current time as a Unix timestamp in seconds.
+pub fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs() as i64)
+}
+
+impl Link {
+    /// A link to `url` created now.
+    pub fn new(url: Url) -> Self {
+        Self::with_meta(url, Meta::default())
+    }
+
+    pub fn with_meta(url: Url, meta: Meta) -> Self {
+        let now = now();
+
+        Self {
+            url,
+            created_at: now,
+            updated_at: now,
+            meta,
+        }
+    }
+
+    /// The link as anyone may see it, without `owner` and `notes`.
+    pub fn public(self) -> Self {
+        Self {
+            meta: Meta {
+                owner: None,
+                notes: None,
+                ..self.meta
+            },
+            ..self
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn metadata_is_flattened() {
+        let link = Link {
+            url: "https://blazinglyfast.net/".parse().unwrap(),
+            created_at: 1,
+            updated_at: 2,
+            meta: Meta {
+                title: Some("Blazingly Fast".into()),
+                tags: vec!["rust".into()],
+                ..Meta::default()
+            },
+        };
+
+        assert_eq!(
+            serde_json::to_string(&link).unwrap(),
+            r#"{"url":"https://blazinglyfast.net/","created_at":1,"updated_at":2,"title":"Blazingly Fast","tags":["rust"]}"#
+        );
+    }
+
+    #[test]
+    fn missing_fields_default() {
+        let link: Link = serde_json::from_str(r#"{"url":"https://blazinglyfast.net/"}"#).unwrap();
+
+        assert_eq!(link.created_at, 0);
+        assert_eq!(link.meta, Meta::default());
+    }
+
+    #[test]
+    fn public_links_leave_out_owner_and_notes() {
+        let link = Link::with_meta(
+            "https://blazinglyfast.net/".parse().unwrap(),
+            Meta {
+                owner: Some("ferris".into()),
+                title: Some("Blazingly Fast".into()),
+                notes: Some("internal".into()),
+                ..Meta::default()
+            },
+        )
+        .public();
+
+        assert_eq!(
+            link.meta,
+            Meta {
+                title: Some("Blazingly Fast".into()),
+                ..Meta::default()
+            }
+        );
+    }
+}
@@ -0,0 +1,151 @@
+use serde::Deserialize;
+
+use crate::{link::Link, validator::Code};
+
+/// Links listed per page unless asked otherwise.
+pub const DEFAULT_LIMIT: usize = 50;
+
+/// Most links listed per page.
+pub const MAX_LIMIT: usize = 1000;
+
+/// Which stored links to list, and where the page starts.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct Query {
+    pub owner: Option<String>,
+    pub tag: Option<String>,
+    /// Only links created at or after this Unix timestamp.
+    pub created_after: Option<i64>,
+    /// Only links created before this Unix timestamp.
+    pub created_before: Option<i64>,
+    /// Substring of the destination or code, ignoring case.
+    #[serde(rename = "q")]
+    pub search: Option<String>,
+    /// The `next` cursor of the previous page.
+    pub cursor: Option<String>,
+    pub limit: Option<usize>,
+}
+
+/// A page of links, ordered by code except on Redis.
+#[derive(Debug, Default)]
+pub struct Page {
+    pub links: Vec<(Code, Link)>,
+    /// Where the next page starts, `None` on the last one.
+    pub next: Option<String>,
+}
+
+impl Query {
+    /// Links per page, within `1..=MAX_LIMIT`.
+    pub fn limit(&self) -> usize {
+        self.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT)
+    }
+
+    /// The search string in the form it's compared in.
+    pub fn search_folded(&self) -> Option<String> {
+        self.search.as_deref().map(str::to_lowercase)
+    }
+
+    /// Whether the link stored under `code` is listed, leaving out the
+    /// cursor.
+    pub fn matches(&self, code: &Code, link: &Link) -> bool {
+        let owner = self
+            .owner
+            .as_ref()
+            .is_none_or(|owner| link.meta.owner.as_ref() == Some(owner));
+        let tag = self
+            .tag
+            .as_ref()
+            .is_none_or(|tag| link.meta.tags.contains(tag));
+        let after = self
+            .created_after
+            .is_none_or(|after| link.created_at >= after);
+        let before = self
+            .created_before
+            .is_none_or(|before| link.created_at < before);
+        let search = self.search_folded().is_none_or(|search| {
+            link.url.as_str().to_lowercase().contains(&search)
+                || code.as_str().to_lowercase().contains(&search)
+        });
+
+        owner && tag && after && before && search
+    }
+}
+
+impl Page {
+    /// A page of at most `limit` links from `links`, which are ordered by
+    /// code and hold one more link if there's a next page.
+    pub fn ordered(mut links: Vec<(Code, Link)>, limit: usize) -> Self {
+        let next = match links.len() > limit {
+            true => {
+                links.truncate(limit);
+                links.last().map(|(code, _)| code.as_str().to_string())
+            }
+            false => None,
+        };
+
+        Self { links, next }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::link::Meta;
+
+    fn link(url: &str, created_at: i64, tags: &[&str]) -> Link {
+        Link {
+            url: url.parse().unwrap(),
+            created_at,
+            updated_at: created_at,
+            meta: Meta {
+                owner: Some("ferris".into()),
+                tags: tags.iter().map(|tag| tag.to_string()).collect(),
+                ..Meta::default()
+            },
+        }
+    }
+
+    #[test]
+    fn filters_combine() {
+        let query = Query {
+            tag: Some("rust".into()),
+            created_after: Some(10),
+            search: Some("FAST".into()),
+            ..Query::default()
+        };
+        let code = Code::new("blaze".into());
+
+        assert!(query.matches(&code, &link("https://blazinglyfast.net/", 10, &["rust"])));
+        assert!(!query.matches(&code, &link("https://blazinglyfast.net/", 9, &["rust"])));
+        assert!(!query.matches(&code, &link("https://blazinglyfast.net/", 10, &[])));
+        assert!(!query.matches(&code, &link("https://example.com/", 10, &["rust"])));
+    }
+
+    #[test]
+    fn search_covers_codes() {
+        let query = Query {
+            search: Some("laz".into()),
+            ..Query::default()
+        };
+
+        assert!(query.matches(
+            &Code::new("blaze".into()),
+            &link("https://example.com/", 0, &[])
+        ));
+    }
+
+    #[test]
+    fn pages_end_at_the_last_code() {
+        let links: Vec<_> = ["a", "b", "c"]
+            .iter()
+            .map(|code| {
+                (
+                    Code::new(code.to_string()),
+                    link("https://example.com/", 0, &[]),
+                )
+            })
+            .collect();
+
+        assert_eq!(Page::ordered(links.clone(), 2).next.as_deref(), Some("b"));
+        assert_eq!(Page::ordered(links, 3).next, None);
+    }
+}
@@ -0,0 +1,165 @@
+use url::{Host, Url};
+
+use crate::canonical::Canonicalizer;
+
+/// What links are looked up by, in reverse.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Target {
+    /// Links to exactly this URL.
+    Url(Url),
+    /// Links to this host only.
+    Host(String),
+    /// Links to this domain and its subdomains.
+    Domain(String),
+}
+
+/// A host as it's stored in URLs: lowercase, IDNA-encoded and without a
+/// trailing dot. `None` if it isn't a valid host.
+pub fn normalize_host(host: &str) -> Option<String> {
+    let host = host.trim().trim_end_matches('.');
+
+    match host.is_empty() {
+        true => None,
+        false => Host::parse(host).ok().map(|host| host.to_string()),
+    }
+}
+
+/// The labels of `host` reversed, so that subdomains sort right after their
+/// domain: `www.example.com` is kept as `com.example.www.`.
+pub fn domain_key(host: &str) -> String {
+    let mut key: String = host.rsplit('.').flat_map(|label| [label, "."]).collect();
+
+    // IP addresses aren't domains, they're kept as they are.
+    if host.starts_with('[') || host.parse::<std::net::Ipv4Addr>().is_ok() {
+        key = format!("{host}.");
+    }
+
+    key
+}
+
+/// The key of a URL's host, `None` for URLs without one.
+pub fn url_key(url: &Url) -> Option<String> {
+    url.host_str().map(domain_key)
+}
+
+/// Keys of `domain` and its subdomains are in `lower..upper`.
+pub fn domain_range(domain: &str) -> (String, String) {
+    let lower = domain_key(domain);
+    // `/` comes right after `.`, which every key ends with.
+    let upper = format!("{}/", &lower[..lower.len() - 1]);
+
+    (lower, upper)
+}
+
+impl Target {
+    /// A lookup by host, normalized. `None` if `host` isn't valid.
+    pub fn host(host: &str) -> Option<Self> {
+        normalize_host(host).map(Target::Host)
+    }
+
+    /// A lookup by domain, normalized. `None` if `domain` isn't valid.
+    pub fn domain(domain: &str) -> Option<Self> {
+        normalize_host(domain).map(Target::Domain)
+    }
+
+    /// The lookup with its URL in the canonical form links are stored in.
+    pub fn canonical(self, canonical: &Canonicalizer) -> Self {
+        match self {
+            Target::Url(url) => Target::Url(canonical.apply(url)),
+            target => target,
+        }
+    }
+
+    pub fn matches(&self, url: &Url) -> bool {
+        match self {
+            Target::Url(target) => url == target,
+            Target::Host(host) => url.host_str() == Some(host),
+            Target::Domain(domain) => url.host_str().is_some_and(|host| on_domain(host, domain)),
+        }
+    }
+}
+
+/// Whether `host` is `domain` or one of its subdomains.
+fn on_domain(host: &str, domain: &str) -> bool {
+    host == domain
+        || host
+            .strip_suffix(domain)
+            .is_some_and(|sub| sub.ends_with('.'))
+}
+
+/// `url` moved from domain `from` to `to`, keeping subdomains, e.g.
+/// `docs.old.com` becomes `docs.new.com`. `None` if `url` isn't on `from`.
+pub fn retarget(url: &Url, from: &str, to: &str) -> Option<Url> {
+    let host = url.host_str().filter(|host| on_domain(host, from))?;
+    let host = format!("{}{to}", &host[..host.len() - from.len()]);
+
+    let mut url = url.clone();
+    url.set_host(Some(&host)).ok()?;
+
+    Some(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(url: &str) -> Url {
+        url.parse().unwrap()
+    }
+
+    #[test]
+    fn subdomains_sort_after_their_domain() {
+        let (lower, upper) = domain_range("example.com");
+
+        assert_eq!(lower, "com.example.");
+        for host in ["example.com", "www.example.com", "a.b.example.com"] {
+            let key = domain_key(host);
+            assert!(lower <= key && key < upper, "{host}");
+        }
+        for host in ["examples.com", "example.org", "com"] {
+            let key = domain_key(host);
+            assert!(!(lower <= key && key < upper), "{host}");
+        }
+    }
+
+    #[test]
+    fn hosts_are_normalized() {
+        assert_eq!(normalize_host("Example.COM."), Some("example.com".into()));
+        assert_eq!(normalize_host("bücher.de"), Some("xn--bcher-kva.de".into()));
+        assert_eq!(normalize_host(""), None);
+    }
+
+    #[test]
+    fn domains_match_subdomains_only() {
+        let target = Target::domain("example.com").unwrap();
+
+        assert!(target.matches(&url("https://docs.example.com/")));
+        assert!(!target.matches(&url("https://badexample.com/")));
+        assert!(!Target::host("example.com")
+            .unwrap()
+            .matches(&url("https://docs.example.com/")));
+    }
+
+    #[test]
+    fn retargeting_keeps_the_rest() {
+        let moved = retarget(
+            &url("https://docs.old.com:8443/a?b#c"),
+            "old.com",
+            "new.org",
+        );
+
+        assert_eq!(moved, Some(url("https://docs.new.org:8443/a?b#c")));
+        assert_eq!(
+            retarget(&url("https://bold.com/"), "old.com", "new.org"),
+            None
+        );
+    }
+
+    #[test]
+    fn urls_are_looked_up_in_canonical_form() {
+        let target = Target::Url(url("https://Example.com:443/a/../b?utm_source=x"))
+            .canonical(&Canonicalizer::default());
+
+        assert!(target.matches(&url("https://example.com/b")));
+    }
+}
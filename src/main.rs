@@ -1,40 +1,199 @@
+mod cli;
 mod config;
 mod route;
 
+use cli::{Args, Command};
+
 use config::Config;
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc, time::Duration};
 use tokio::sync::RwLock;
 
-use axum::{
-    routing::{get, post},
-    Router,
-};
+use axum::extract::DefaultBodyLimit;
+use axum::routing::{get, post};
+use axum::ServiceExt;
+use tower::ServiceExt as _;
 
 use shrink::{
-    app::{App, AppState},
+    app::{App, AppState, Checks},
+    canonical::Canonicalizer,
+    chain::Chains,
+    filter::DomainFilter,
+    generators::{Filtered, KeyPool, Words, RB62},
+    policy::Policy,
     storage::Redis,
-    validator::{Alnum, Validator},
+    suggest::Suggester,
+    transfer,
+    validator::{self, DefaultValidator, Profanity, Reserved, Rule, Slug, Unicode, Validator},
+    Generator,
 };
 
+/// Where links are kept.
+const DATABASE: &str = "data/urls.db";
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let redis_client = Redis::default();
-
-    let app = App::open("data/urls.db")?.with_cache(redis_client);
-    let app = Arc::new(RwLock::new(app));
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(usage) => {
+            eprintln!("{usage}");
+            std::process::exit(2);
+        }
+    };
 
     let config = Config::from_env().unwrap_or_default();
 
+    let profanity = Arc::new(match &config.blocklist {
+        Some(path) => Profanity::from_file(path)?,
+        None => Profanity::default(),
+    });
+
+    let mut checksum = None;
+
+    let codes: Box<dyn Generator> = match config.words {
+        Some(count) => Box::new(Filtered::new(
+            Words::new(count, config.separator),
+            profanity.clone(),
+        )),
+        None => {
+            let codes = match config.unambiguous {
+                true => RB62::unambiguous(),
+                false => RB62::default(),
+            };
+            let codes = match config.check {
+                true => codes.with_check(),
+                false => codes,
+            };
+            checksum = codes.checksum();
+
+            Box::new(Filtered::new(codes, profanity.clone()))
+        }
+    };
+
+    let routes = route::Routes::default()
+        .route("/", post(route::shrink).put(route::custom_code))
+        .route("/{code}", get(route::redirect))
+        .route("/{code}/info", get(route::info))
+        .route("/bulk", post(route::bulk))
+        .route("/aliases/{alias}", get(route::alias))
+        .route("/admin/links", get(route::list))
+        .route("/admin/lookup", get(route::lookup))
+        .route("/admin/retarget", post(route::retarget))
+        .route("/admin/export", get(route::export))
+        .route(
+            "/admin/import",
+            post(route::import).layer(DefaultBodyLimit::max(route::IMPORT_LIMIT)),
+        );
+
+    let reserved = Reserved::default()
+        .with_routes(routes.paths().iter().copied())
+        .extend(config.reserved);
+
+    let unicode = Arc::new(Unicode::new(
+        config.alias_length.0,
+        config.alias_length.1,
+        config.separator,
+    ));
+
+    // Rules validation chains can be built from.
+    let rules: HashMap<&str, Rule> = HashMap::from([
+        ("alnum", Arc::new(validator::Alnum) as Rule),
+        ("slug", Arc::new(Slug::new(config.separator))),
+        ("path", Arc::new(DefaultValidator::default())),
+        ("unicode", unicode.clone()),
+        ("unreserved", Arc::new(reserved)),
+        ("clean", Arc::new((*profanity).clone().whole_words())),
+    ]);
+
+    let code_rules = config.code_rules.as_deref().unwrap_or("slug");
+    let alias_rules = config
+        .alias_rules
+        .as_deref()
+        .unwrap_or(match config.filter_aliases {
+            true => "unicode; unreserved; clean",
+            false => "unicode; unreserved",
+        });
+
+    let validator = Arc::new(Validator::new(validator::parse(code_rules, &rules)?));
+    let aliases = Arc::new(Validator::new(validator::parse(alias_rules, &rules)?));
+    // Prefixes end up in generated codes, the filter of which would reject
+    // every code if a prefix had an offensive word in it.
+    let mut prefixes = validator::parse(code_rules, &rules)?;
+    prefixes.0.push(profanity);
+    if let Some(checksum) = checksum {
+        prefixes.0.push(Arc::new(checksum.alphabet));
+    }
+    let prefixes = Arc::new(Validator::new(prefixes));
+
+    let mut own_urls = config.own_urls;
+    own_urls.push(config.server_url.clone());
+
+    let policy = Arc::new(Policy::new(
+        config.schemes,
+        config.ports,
+        config.allow_private,
+    ));
+    let domains = Arc::new(DomainFilter::from_files(
+        config.domain_blocklist,
+        config.domain_allowlist,
+    )?);
+    let chains = Arc::new(Chains::new(own_urls, config.shorteners));
+    let canonical = Arc::new(Canonicalizer::new(config.canonical, config.tracking));
+
+    // Commands other than serving don't need the server's storage or cache.
+    if !matches!(args.command, Command::Serve) {
+        let checks = Checks {
+            canonical: &canonical,
+            chains: &chains,
+            policy: &policy,
+            domains: &domains,
+        };
+
+        return args
+            .run(DATABASE, config.case_insensitive, &aliases, &checks)
+            .await;
+    }
+
+    domains.watch(Duration::from_secs(5));
+
+    let mut app = App::open(DATABASE)?;
+
+    if config.case_insensitive {
+        app.urls = app.urls.case_insensitive()?;
+    }
+
+    // Aliases confusable with stored codes are rejected, not only with the
+    // ones created since startup.
+    transfer::each(&app.urls, |code, _| {
+        unicode.register(code.as_str());
+        Ok(())
+    })?;
+
+    let codes = match config.key_pool {
+        Some((low, high)) => KeyPool::spawn(codes, app.urls.clone(), low, high),
+        None => KeyPool::inline(codes),
+    };
+
+    let app = app.with_codes(codes).with_cache(Redis::default());
+    let app = Arc::new(RwLock::new(app));
+
     let app = AppState {
         app,
         base_url: config.server_url,
-        validator: Arc::new(Validator::new(Alnum)),
+        validator,
+        aliases,
+        prefixes,
+        unicode,
+        checksum,
+        policy,
+        domains,
+        recheck: config.recheck,
+        chains,
+        canonical,
+        suggester: Arc::new(Suggester::new(config.separator, 5)),
+        admin_token: config.admin_token.map(Arc::from),
     };
 
-    let router = Router::new()
-        .route("/", post(route::shrink).put(route::custom_code))
-        .route("/{code}", get(route::redirect))
-        .with_state(app);
+    let router = routes.with_state(app);
 
     // TODO: Add a tracing layer.
 
@@ -46,7 +205,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     //let signals = signal::ctrl_c();
 
-    axum::serve(listener, router)
+    // Rewrites have to happen before routing.
+    let router = router.map_request(route::preview_shortcut);
+
+    axum::serve(listener, router.into_make_service())
         //.with_graceful_shutdown(async move {
         //    if let Err(e) = signals.await {
         //        eprintln!("error during shutdown: {}", e);
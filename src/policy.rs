@@ -0,0 +1,231 @@
+use std::fmt::Display;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use url::{Host, Url};
+
+/// Rules a destination URL has to follow before it's shrunk.
+pub struct Policy {
+    schemes: Vec<String>,
+    /// Ports that may be set explicitly, any port if `None`.
+    ports: Option<Vec<u16>>,
+    allow_private: bool,
+}
+
+/// Why a destination was rejected.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Violation {
+    Scheme(String),
+    NoHost,
+    PrivateHost(String),
+    Port(u16),
+}
+
+/// Host names that always point back into the network we run in.
+const PRIVATE_NAMES: &[&str] = &["localhost", "metadata.google.internal"];
+const PRIVATE_SUFFIXES: &[&str] = &[".localhost", ".local", ".internal", ".home.arpa"];
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Violation::Scheme(scheme) => write!(f, "scheme `{scheme}` is not allowed"),
+            Violation::NoHost => write!(f, "destination has no host"),
+            Violation::PrivateHost(host) => {
+                write!(f, "host `{host}` is private, loopback or link-local")
+            }
+            Violation::Port(port) => write!(f, "port {port} is not allowed"),
+        }
+    }
+}
+
+impl std::error::Error for Violation {}
+
+fn is_private_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+
+    ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        // Shared address space (RFC 6598), used by carrier-grade NATs.
+        || (a == 100 && (64..128).contains(&b))
+}
+
+fn is_private_v6(ip: Ipv6Addr) -> bool {
+    if let Some(v4) = ip.to_ipv4_mapped() {
+        return is_private_v4(v4);
+    }
+
+    let first = ip.segments()[0];
+
+    ip.is_loopback()
+        || ip.is_unspecified()
+        // Unique local (fc00::/7) and link-local (fe80::/10) addresses.
+        || (first & 0xfe00) == 0xfc00
+        || (first & 0xffc0) == 0xfe80
+}
+
+impl Policy {
+    pub fn new(schemes: Vec<String>, ports: Option<Vec<u16>>, allow_private: bool) -> Self {
+        Self {
+            schemes: schemes.into_iter().map(|s| s.to_lowercase()).collect(),
+            ports,
+            allow_private,
+        }
+    }
+
+    pub fn check(&self, url: &Url) -> Result<(), Violation> {
+        self.check_scheme(url)?;
+
+        let host = url.host().ok_or(Violation::NoHost)?;
+
+        if !self.allow_private {
+            let private = match &host {
+                Host::Domain(name) => {
+                    let name = name.trim_end_matches('.');
+                    PRIVATE_NAMES.contains(&name)
+                        || PRIVATE_SUFFIXES.iter().any(|suffix| name.ends_with(suffix))
+                }
+                Host::Ipv4(ip) => is_private_v4(*ip),
+                Host::Ipv6(ip) => is_private_v6(*ip),
+            };
+
+            if private {
+                return Err(Violation::PrivateHost(host.to_string()));
+            }
+        }
+
+        // `Url::port` is `None` for the default port of the scheme.
+        match (url.port(), &self.ports) {
+            (Some(port), Some(ports)) if !ports.contains(&port) => Err(Violation::Port(port)),
+            (Some(0), _) => Err(Violation::Port(0)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Only checks the scheme, e.g. of a stored destination that's linked
+    /// to, where `javascript:` or `data:` would run on our pages.
+    pub fn check_scheme(&self, url: &Url) -> Result<(), Violation> {
+        match self.schemes.iter().any(|scheme| scheme == url.scheme()) {
+            true => Ok(()),
+            false => Err(Violation::Scheme(url.scheme().to_string())),
+        }
+    }
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Self::new(vec!["http".into(), "https".into()], None, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(url: &str) -> Result<(), Violation> {
+        Policy::default().check(&url.parse().unwrap())
+    }
+
+    #[test]
+    fn public_https_is_allowed() {
+        assert_eq!(check("https://blazinglyfast.net/"), Ok(()))
+    }
+
+    #[test]
+    fn dangerous_schemes_are_rejected() {
+        assert!(matches!(
+            check("javascript:alert(1)"),
+            Err(Violation::Scheme(_))
+        ));
+        assert!(matches!(
+            check("data:text/html,hi"),
+            Err(Violation::Scheme(_))
+        ));
+        assert!(matches!(
+            check("file:///etc/passwd"),
+            Err(Violation::Scheme(_))
+        ));
+    }
+
+    #[test]
+    fn schemes_can_be_checked_alone() {
+        let policy = Policy::default();
+        let scheme = |url: &str| policy.check_scheme(&url.parse().unwrap());
+
+        assert_eq!(scheme("http://localhost/"), Ok(()));
+        assert!(scheme("javascript:alert(document.cookie)").is_err());
+        assert!(scheme("data:text/html,<script>alert(1)</script>").is_err());
+    }
+
+    #[test]
+    fn loopback_is_rejected() {
+        assert!(matches!(
+            check("http://127.0.0.1/"),
+            Err(Violation::PrivateHost(_))
+        ));
+        assert!(matches!(
+            check("http://[::1]/"),
+            Err(Violation::PrivateHost(_))
+        ));
+        assert!(matches!(
+            check("http://localhost:3000/"),
+            Err(Violation::PrivateHost(_))
+        ));
+    }
+
+    #[test]
+    fn encoded_loopback_is_rejected() {
+        assert!(matches!(
+            check("http://2130706433/"),
+            Err(Violation::PrivateHost(_))
+        ));
+        assert!(matches!(
+            check("http://[::ffff:127.0.0.1]/"),
+            Err(Violation::PrivateHost(_))
+        ));
+    }
+
+    #[test]
+    fn private_ranges_are_rejected() {
+        for url in [
+            "http://10.0.0.1/",
+            "http://172.16.3.4/",
+            "http://192.168.1.1/",
+            "http://169.254.169.254/latest/meta-data",
+            "http://[fd00::1]/",
+            "http://[fe80::1]/",
+            "http://printer.local/",
+        ] {
+            assert!(
+                matches!(check(url), Err(Violation::PrivateHost(_))),
+                "{url}"
+            );
+        }
+    }
+
+    #[test]
+    fn private_hosts_can_be_allowed() {
+        let policy = Policy::new(vec!["http".into()], None, true);
+        assert_eq!(policy.check(&"http://10.0.0.1/".parse().unwrap()), Ok(()));
+    }
+
+    #[test]
+    fn ports_can_be_restricted() {
+        let policy = Policy::new(vec!["https".into()], Some(vec![8443]), false);
+
+        assert_eq!(
+            policy.check(&"https://example.com/".parse().unwrap()),
+            Ok(())
+        );
+        assert_eq!(
+            policy.check(&"https://example.com:8443/".parse().unwrap()),
+            Ok(())
+        );
+        assert_eq!(
+            policy.check(&"https://example.com:22/".parse().unwrap()),
+            Err(Violation::Port(22))
+        );
+    }
+}
@@ -0,0 +1,157 @@
+use url::Url;
+
+use crate::link::Link;
+
+/// Escapes text for use in HTML, including attribute values.
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// Whether the `Accept` header prefers HTML over JSON. JSON wins ties and
+/// missing headers.
+pub fn wants_html(accept: Option<&str>) -> bool {
+    let mut html = 0.0;
+    let mut json = 0.0;
+
+    for range in accept.unwrap_or_default().split(',') {
+        let mut params = range.split(';').map(str::trim);
+        let kind = params.next().unwrap_or_default();
+        let q = params
+            .find_map(|param| param.strip_prefix("q="))
+            .and_then(|q| q.parse().ok())
+            .unwrap_or(1.0);
+
+        match kind {
+            "text/html" => html = f32::max(html, q),
+            "application/json" | "application/*" | "*/*" => json = f32::max(json, q),
+            _ => (),
+        }
+    }
+
+    html > json
+}
+
+/// Renders the page showing where `shrunk` leads before following it.
+pub fn render(shrunk: &Url, link: &Link) -> String {
+    let meta = &link.meta;
+    let host = link.url.host_str().unwrap_or(link.url.as_str());
+
+    let mut details = String::new();
+    let mut detail = |name: &str, value: String| {
+        details.push_str(&format!("<dt>{name}</dt><dd>{value}</dd>"));
+    };
+
+    if !meta.tags.is_empty() {
+        let tags: Vec<String> = meta
+            .tags
+            .iter()
+            .map(|tag| format!(r#"<span class="tag">{}</span>"#, escape(tag)))
+            .collect();
+        detail("Tags", tags.concat());
+    }
+
+    fill(
+        include_str!("preview.html"),
+        &[
+            ("heading", &escape(meta.title.as_deref().unwrap_or(host))),
+            ("shrunk", &escape(shrunk.as_str())),
+            ("url", &escape(link.url.as_str())),
+            ("host", &escape(host)),
+            ("details", &details),
+        ],
+    )
+}
+
+/// Replaces the `{name}` placeholders of `template` with their values in a
+/// single pass, so placeholders in values are left alone. Other braces, like
+/// those of the styles, are kept.
+fn fill(template: &str, values: &[(&str, &str)]) -> String {
+    let mut filled = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        filled.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let value = rest.find('}').and_then(|end| {
+            let (_, value) = values.iter().find(|(name, _)| *name == &rest[1..end])?;
+            Some((value, end))
+        });
+
+        match value {
+            Some((value, end)) => {
+                filled.push_str(value);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                filled.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    filled.push_str(rest);
+    filled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::link::Meta;
+
+    #[test]
+    fn browsers_get_html() {
+        let firefox = "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8";
+
+        assert!(wants_html(Some(firefox)));
+        assert!(!wants_html(Some("*/*")));
+        assert!(!wants_html(Some("application/json, text/html")));
+        assert!(!wants_html(None));
+    }
+
+    #[test]
+    fn escapes_metadata() {
+        let link = Link::with_meta(
+            "https://blazinglyfast.net/?a=1&b=2".parse().unwrap(),
+            Meta {
+                title: Some("<script>alert(1)</script>".into()),
+                tags: vec![r#"" onclick="x"#.into()],
+                ..Meta::default()
+            },
+        );
+        let page = render(&"http://localhost:3000/blaze".parse().unwrap(), &link);
+
+        assert!(!page.contains("<script>"));
+        assert!(page.contains("&lt;script&gt;alert(1)&lt;/script&gt;"));
+        assert!(page.contains("&quot; onclick=&quot;x"));
+        assert!(page.contains(r#"href="https://blazinglyfast.net/?a=1&amp;b=2""#));
+    }
+
+    #[test]
+    fn placeholders_in_values_are_kept() {
+        let link = Link::with_meta(
+            "https://blazinglyfast.net/".parse().unwrap(),
+            Meta {
+                title: Some("{url} and {details}".into()),
+                ..Meta::default()
+            },
+        );
+        let page = render(&"http://localhost:3000/blaze".parse().unwrap(), &link);
+
+        assert!(page.contains("<h1>{url} and {details}</h1>"));
+        assert!(page.contains("body { font-family"));
+    }
+}
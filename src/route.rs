@@ -1,52 +1,141 @@
+use std::convert::Infallible;
+
 use axum::{
-    extract::{Path, State},
-    response::Redirect,
-    Json,
+    body::{Body, Bytes},
+    extract::{Request, State},
+    http::{header, HeaderMap, StatusCode, Uri},
+    response::{Html, IntoResponse, Redirect, Response},
+    routing::MethodRouter,
+    Router,
+};
+use futures_util::{
+    stream::{self, BoxStream},
+    Stream, StreamExt,
 };
-use shrink::{app::AppState, error, Shrinker, Storage};
+use shrink::{
+    app::{AppState, Shrunk},
+    error::{self, Problem},
+    extract::{Admin, Aliases, Json, Path, Prefixes, Query, Unchecked, Valid},
+    generators::Context,
+    link::{Link, Meta},
+    listing,
+    lookup::Target,
+    preview,
+    transfer::{
+        self,
+        foreign::{self, Source},
+        Format, OnConflict,
+    },
+    validator::Code,
+    Storage,
+};
+use tokio::sync::mpsc;
 use url::Url;
 
+/// Router that keeps track of its paths, so that custom aliases can't
+/// shadow them.
+#[derive(Default)]
+pub struct Routes {
+    router: Router<AppState>,
+    paths: Vec<&'static str>,
+}
+
+impl Routes {
+    pub fn route(self, path: &'static str, method_router: MethodRouter<AppState>) -> Self {
+        let mut paths = self.paths;
+        paths.push(path);
+
+        Self {
+            router: self.router.route(path, method_router),
+            paths,
+        }
+    }
+
+    pub fn paths(&self) -> &[&'static str] {
+        &self.paths
+    }
+
+    pub fn with_state(self, state: AppState) -> Router {
+        self.router.with_state(state)
+    }
+}
+
 #[derive(serde::Serialize)]
 pub struct ShrinkResponse {
     shrunk: Url,
+    /// Things the client may want to know about the destination, e.g. that
+    /// it's another shortener's link.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    warnings: Vec<String>,
 }
 
 #[derive(serde::Deserialize)]
 pub struct ShrinkRequest {
     url: Url,
+    /// What the generated code should start with, if the generator supports it.
+    prefix: Option<Unchecked<Prefixes>>,
+    tenant: Option<String>,
+    #[serde(flatten)]
+    meta: Meta,
 }
 
 #[derive(serde::Deserialize)]
 pub struct CustomShrinkRequest {
-    alias: String,
+    alias: Unchecked<Aliases>,
     url: Url,
+    #[serde(flatten)]
+    meta: Meta,
 }
 
 pub async fn shrink(
     State(state): State<AppState>,
     body: Json<ShrinkRequest>,
-) -> Result<Json<ShrinkResponse>, error::Internal> {
-    let ShrinkRequest { url } = body.0;
+) -> Result<Json<ShrinkResponse>, error::Error> {
+    let ShrinkRequest {
+        url,
+        prefix,
+        tenant,
+        meta,
+    } = body.0;
+
+    let (url, warnings) = state.vet(url).await?;
+
+    let prefix = match prefix {
+        Some(prefix) => Some(prefix.check(&state)?),
+        None => None,
+    };
+
+    let context = Context::new(&url)
+        .with_prefix(prefix.as_ref().map(Code::as_str))
+        .with_tenant(tenant.as_deref());
+
     // XXX: Maybe inefficient because of locking the entire database?
-    let code = state.app.write().await.shrink(url)?;
+    let link = Link::with_meta(url.clone(), meta);
+    let code = state.app.write().await.shrink_with(link, &context)?;
+    state.unicode.register(code.as_str());
 
     // #WET-02: Response generation
     state
         .shrink_response(&code)
-        .ok_or(error::Internal("Failed to generate a code.".into()))
-        .map(|url| Json(ShrinkResponse { shrunk: url }))
+        .ok_or(error::Error::Internal("Failed to generate a code.".into()))
+        .map(|url| {
+            Json(ShrinkResponse {
+                shrunk: url,
+                warnings,
+            })
+        })
 }
 
 pub async fn redirect(
     State(state): State<AppState>,
-    Path(code): Path<String>,
-) -> Result<Redirect, error::Load> {
-    // #WET-01: Validation
-    let code = state
-        .validator
-        .validate(code)
-        .ok_or(error::Load::BadAlias)?;
-    let url = state.app.read().await.expand(&code)?;
+    Valid { code, .. }: Valid,
+) -> Result<Redirect, error::Error> {
+    let url = state.load(&code).await?.url;
+
+    if state.recheck && state.domains.check(&url).is_err() {
+        return Err(error::Error::Blocked);
+    }
+
     // Consider using 302 (Status Found) instead of 307 (Status Temporary Redirect).
     Ok(Redirect::temporary(url.as_str()))
 }
@@ -54,23 +143,491 @@ pub async fn redirect(
 pub async fn custom_code(
     State(state): State<AppState>,
     body: Json<CustomShrinkRequest>,
-) -> Result<Json<ShrinkResponse>, error::Storage> {
-    let CustomShrinkRequest { url, alias: code } = body.0;
+) -> Result<Json<ShrinkResponse>, error::Error> {
+    let CustomShrinkRequest { url, alias, meta } = body.0;
 
-    // #WET-01: Validation
-    // XXX: Use a deserializer or middleware to DRY this up?
-    let code = state
-        .validator
-        .validate(code)
-        .ok_or(error::Storage::BadAlias)?;
+    let (url, warnings) = state.vet(url).await?;
+    let code = alias.check(&state)?;
 
-    state.app.write().await.urls.store(url, &code)?;
+    let link = Link::with_meta(url, meta);
+    state.app.write().await.urls.store(link, &code)?;
+    state.unicode.register(code.as_str());
 
     // #WET-02: Response generation
     state
         .shrink_response(&code)
-        .ok_or(error::Storage::Internal(
-            "Failed to generate a code.".into(),
+        .ok_or(error::Error::Internal("Failed to generate a code.".into()))
+        .map(|url| {
+            Json(ShrinkResponse {
+                shrunk: url,
+                warnings,
+            })
+        })
+}
+
+#[derive(serde::Deserialize)]
+pub struct AliasQuery {
+    /// Destination the alias is meant for, to base suggestions on.
+    url: Option<Url>,
+}
+
+#[derive(serde::Serialize)]
+pub struct AliasResponse {
+    alias: String,
+    valid: bool,
+    available: bool,
+    /// Why the alias can't be used.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    suggestions: Vec<String>,
+}
+
+pub async fn alias(
+    State(state): State<AppState>,
+    Path(alias): Path<String>,
+    Query(query): Query<AliasQuery>,
+) -> Result<Json<AliasResponse>, error::Error> {
+    let (alias, valid, available, reason) =
+        match Unchecked::<Aliases>::new(alias.clone()).check(&state) {
+            Ok(code) => match state.available(&code).await? {
+                true => (code.as_str().to_string(), true, true, None),
+                false => (
+                    code.as_str().to_string(),
+                    true,
+                    false,
+                    Some("alias already used".to_string()),
+                ),
+            },
+            Err(invalid) => (alias, false, false, Some(invalid.to_string())),
+        };
+
+    let suggestions = match available {
+        true => Vec::new(),
+        false => state
+            .suggest(&alias, query.url.as_ref())
+            .await
+            .iter()
+            .map(|code| code.as_str().to_string())
+            .collect(),
+    };
+
+    Ok(Json(AliasResponse {
+        alias,
+        valid,
+        available,
+        reason,
+        suggestions,
+    }))
+}
+
+#[derive(serde::Serialize)]
+pub struct InfoResponse {
+    code: Code,
+    shrunk: Url,
+    #[serde(flatten)]
+    link: Link,
+}
+
+/// Shows where a link leads without following it, as JSON or as an HTML
+/// page with a button to continue, depending on `Accept`. Owners and notes
+/// are left to the admin routes.
+pub async fn info(
+    State(state): State<AppState>,
+    Valid { code, .. }: Valid,
+    headers: HeaderMap,
+) -> Result<Response, error::Error> {
+    let link = state.load(&code).await?.public();
+
+    if state.recheck && state.domains.check(&link.url).is_err() {
+        return Err(error::Error::Blocked);
+    }
+
+    // Links stored before schemes were checked, or put straight into
+    // storage, could run script from the preview's link to continue.
+    if state.policy.check_scheme(&link.url).is_err() {
+        return Err(error::Error::Blocked);
+    }
+
+    let shrunk = state
+        .shrink_response(&code)
+        .ok_or(error::Error::Internal("Failed to generate a code.".into()))?;
+
+    let accept = headers
+        .get(header::ACCEPT)
+        .and_then(|accept| accept.to_str().ok());
+
+    Ok(match preview::wants_html(accept) {
+        true => Html(preview::render(&shrunk, &link)).into_response(),
+        false => Json(InfoResponse { code, shrunk, link }).into_response(),
+    })
+}
+
+#[derive(serde::Serialize)]
+pub struct ListResponse {
+    links: Vec<InfoResponse>,
+    /// Cursor of the next page, absent on the last one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next: Option<String>,
+}
+
+/// Lists stored links for admins, a page at a time.
+pub async fn list(
+    State(state): State<AppState>,
+    _: Admin,
+    Query(query): Query<listing::Query>,
+) -> Result<Json<ListResponse>, error::Error> {
+    let page = state.list(&query).await?;
+
+    Ok(Json(ListResponse {
+        links: infos(&state, page.links)?,
+        next: page.next,
+    }))
+}
+
+#[derive(serde::Deserialize)]
+pub struct LookupQuery {
+    url: Option<Url>,
+    host: Option<String>,
+    domain: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+pub struct RetargetRequest {
+    from: String,
+    to: String,
+}
+
+impl LookupQuery {
+    fn target(self) -> Result<Target, error::Error> {
+        let invalid = || error::Error::Malformed("invalid host".into());
+
+        match (self.url, self.host, self.domain) {
+            (Some(url), None, None) => Ok(Target::Url(url)),
+            (None, Some(host), None) => Target::host(&host).ok_or_else(invalid),
+            (None, None, Some(domain)) => Target::domain(&domain).ok_or_else(invalid),
+            _ => Err(error::Error::Malformed(
+                "exactly one of url, host or domain is needed".into(),
+            )),
+        }
+    }
+}
+
+/// Links as `info` shows them.
+fn infos(state: &AppState, links: Vec<(Code, Link)>) -> Result<Vec<InfoResponse>, error::Error> {
+    links
+        .into_iter()
+        .map(|(code, link)| {
+            let shrunk = state
+                .shrink_response(&code)
+                .ok_or(error::Error::Internal("Failed to generate a code.".into()))?;
+            Ok(InfoResponse { code, shrunk, link })
+        })
+        .collect()
+}
+
+/// Finds the links pointing at a URL, a host, or a domain and its
+/// subdomains.
+pub async fn lookup(
+    State(state): State<AppState>,
+    _: Admin,
+    Query(query): Query<LookupQuery>,
+) -> Result<Json<ListResponse>, error::Error> {
+    let target = query.target()?.canonical(&state.canonical);
+    let links = state.find(&target).await?;
+
+    Ok(Json(ListResponse {
+        links: infos(&state, links)?,
+        next: None,
+    }))
+}
+
+/// Moves all links on a domain and its subdomains to another domain.
+pub async fn retarget(
+    State(state): State<AppState>,
+    _: Admin,
+    Json(RetargetRequest { from, to }): Json<RetargetRequest>,
+) -> Result<Json<ListResponse>, error::Error> {
+    let links = state.retarget(&from, &to).await?;
+
+    Ok(Json(ListResponse {
+        links: infos(&state, links)?,
+        next: None,
+    }))
+}
+
+/// Largest import accepted, in bytes.
+pub const IMPORT_LIMIT: usize = 64 * 1024 * 1024;
+
+#[derive(serde::Deserialize)]
+pub struct ExportQuery {
+    format: Option<Format>,
+}
+
+#[derive(serde::Deserialize)]
+pub struct ImportQuery {
+    /// Taken from `Content-Type` if unset.
+    format: Option<Format>,
+    /// Another shortener whose export is imported, instead of `format`.
+    from: Option<Source>,
+    #[serde(default)]
+    on_conflict: OnConflict,
+    #[serde(default)]
+    dry_run: bool,
+}
+
+/// Exports every stored link, as NDJSON unless another format is asked
+/// for.
+pub async fn export(
+    State(state): State<AppState>,
+    _: Admin,
+    Query(ExportQuery { format }): Query<ExportQuery>,
+) -> Result<Response, error::Error> {
+    let format = format.unwrap_or(Format::Ndjson);
+    let body = state.export(format).await?;
+
+    Ok(([(header::CONTENT_TYPE, format.content_type())], body).into_response())
+}
+
+/// Imports links with their codes, reporting what was done about each
+/// conflict and error. Nothing is stored on a dry run.
+pub async fn import(
+    State(state): State<AppState>,
+    _: Admin,
+    Query(query): Query<ImportQuery>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Response, error::Error> {
+    let records = match query.from {
+        Some(source) => foreign::read(source, body.as_ref(), &state.aliases)?,
+        None => transfer::read(import_format(&query, &headers)?, body.as_ref())?,
+    };
+    let report = state
+        .import(records, query.on_conflict, query.dry_run)
+        .await;
+
+    let status = match report.ok() {
+        true => StatusCode::OK,
+        false => StatusCode::CONFLICT,
+    };
+
+    Ok((status, Json(report)).into_response())
+}
+
+/// The format asked for, or else the one of `Content-Type`.
+fn import_format(query: &ImportQuery, headers: &HeaderMap) -> Result<Format, error::Error> {
+    query
+        .format
+        .or_else(|| {
+            headers
+                .get(header::CONTENT_TYPE)
+                .and_then(|kind| kind.to_str().ok())
+                .and_then(Format::of_content_type)
+        })
+        .ok_or(error::Error::Malformed(
+            "set format, or Content-Type to text/csv, application/json or application/x-ndjson"
+                .into(),
         ))
-        .map(|url| Json(ShrinkResponse { shrunk: url }))
+}
+
+/// Serves `/{code}+` as `/{code}/info`, since routes can't match on a
+/// suffix.
+pub fn preview_shortcut(mut request: Request) -> Request {
+    let path = request.uri().path();
+
+    if let Some(code) = path.strip_suffix('+').filter(|code| code.len() > 1) {
+        let mut parts = request.uri().clone().into_parts();
+        let path_and_query = match request.uri().query() {
+            Some(query) => format!("{code}/info?{query}"),
+            None => format!("{code}/info"),
+        };
+
+        if let Ok(path_and_query) = path_and_query.parse() {
+            parts.path_and_query = Some(path_and_query);
+            if let Ok(uri) = Uri::from_parts(parts) {
+                *request.uri_mut() = uri;
+            }
+        }
+    }
+
+    request
+}
+
+/// Links shrunk per batch, each batch locks the app once.
+const BULK_BATCH: usize = 500;
+
+/// Largest JSON array of links accepted, in bytes. NDJSON is read as it
+/// streams in, so it isn't held in memory at once.
+pub const BULK_LIMIT: usize = 16 * 1024 * 1024;
+
+#[derive(serde::Deserialize)]
+pub struct BulkItem {
+    url: Url,
+    #[serde(flatten)]
+    meta: Meta,
+}
+
+/// Result of one item of a bulk request, streamed back as a line of NDJSON.
+#[derive(serde::Serialize)]
+pub struct BulkResult {
+    /// Position of the item in the request.
+    index: usize,
+    #[serde(flatten)]
+    outcome: BulkOutcome,
+}
+
+#[derive(serde::Serialize)]
+#[serde(untagged)]
+pub enum BulkOutcome {
+    Shrunk {
+        url: Url,
+        shrunk: Url,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        warnings: Vec<String>,
+    },
+    Failed {
+        error: Problem,
+    },
+}
+
+/// Shrinks many URLs, given as a JSON array or as NDJSON, one object per
+/// line. NDJSON is read as it streams in, and a line of NDJSON is streamed
+/// back per item as batches are done.
+pub async fn bulk(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Body,
+) -> Result<Response, error::Error> {
+    let ndjson = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|kind| kind.to_str().ok())
+        .is_some_and(|kind| {
+            kind.starts_with("application/x-ndjson") || kind.starts_with("application/jsonl")
+        });
+
+    let items: BoxStream<'static, Result<BulkItem, error::Error>> = match ndjson {
+        true => ndjson_items(body).boxed(),
+        false => {
+            let bytes = axum::body::to_bytes(body, BULK_LIMIT).await.map_err(|e| {
+                error::Error::Malformed(format!(
+                    "{e}, send JSON arrays of up to {BULK_LIMIT} bytes, or NDJSON"
+                ))
+            })?;
+            let values: Vec<serde_json::Value> = serde_json::from_slice(&bytes)
+                .map_err(|e| error::Error::Malformed(e.to_string()))?;
+
+            stream::iter(values.into_iter().map(|value| {
+                serde_json::from_value(value).map_err(|e| error::Error::Malformed(e.to_string()))
+            }))
+            .boxed()
+        }
+    };
+
+    let (lines, received) = mpsc::channel(BULK_BATCH);
+    tokio::spawn(shrink_batches(state, items, lines));
+
+    let body = stream::unfold(received, |mut received| async move {
+        received
+            .recv()
+            .await
+            .map(|line| (Ok::<_, Infallible>(line), received))
+    });
+
+    Ok((
+        [(header::CONTENT_TYPE, "application/x-ndjson")],
+        Body::from_stream(body),
+    )
+        .into_response())
+}
+
+/// Items of an NDJSON body, parsed line by line as they arrive.
+fn ndjson_items(body: Body) -> impl Stream<Item = Result<BulkItem, error::Error>> {
+    let parse = |line: &[u8]| {
+        serde_json::from_slice(line).map_err(|e| error::Error::Malformed(e.to_string()))
+    };
+
+    stream::unfold(
+        (body.into_data_stream(), Vec::new(), false),
+        move |(mut chunks, mut buffer, mut done)| async move {
+            loop {
+                if let Some(end) = buffer.iter().position(|&b| b == b'\n') {
+                    let line: Vec<u8> = buffer.drain(..=end).collect();
+                    if line.trim_ascii().is_empty() {
+                        continue;
+                    }
+                    return Some((parse(&line), (chunks, buffer, done)));
+                }
+
+                if done {
+                    if buffer.trim_ascii().is_empty() {
+                        return None;
+                    }
+                    let line = std::mem::take(&mut buffer);
+                    return Some((parse(&line), (chunks, buffer, done)));
+                }
+
+                match chunks.next().await {
+                    Some(Ok(chunk)) => buffer.extend_from_slice(&chunk),
+                    Some(Err(e)) => {
+                        done = true;
+                        buffer.clear();
+                        return Some((
+                            Err(error::Error::Malformed(e.to_string())),
+                            (chunks, buffer, done),
+                        ));
+                    }
+                    None => done = true,
+                }
+            }
+        },
+    )
+}
+
+/// Shrinks `items` in batches, sending a line of NDJSON per item.
+async fn shrink_batches(
+    state: AppState,
+    items: BoxStream<'static, Result<BulkItem, error::Error>>,
+    lines: mpsc::Sender<String>,
+) {
+    let mut batches = items.enumerate().ready_chunks(BULK_BATCH);
+
+    while let Some(batch) = batches.next().await {
+        let links = batch
+            .iter()
+            .filter_map(|(_, item)| item.as_ref().ok())
+            .map(|item| Link::with_meta(item.url.clone(), item.meta.clone()))
+            .collect();
+        let mut shrunk = state.shrink_many(links).await.into_iter();
+
+        for (index, item) in batch {
+            let outcome = item
+                .and_then(|_| shrunk.next().expect("a result for every link"))
+                .and_then(
+                    |Shrunk {
+                         code,
+                         url,
+                         warnings,
+                     }| {
+                        let shrunk = state
+                            .shrink_response(&code)
+                            .ok_or(error::Error::Internal("Failed to generate a code.".into()))?;
+                        Ok(BulkOutcome::Shrunk {
+                            url,
+                            shrunk,
+                            warnings,
+                        })
+                    },
+                )
+                .unwrap_or_else(|e| BulkOutcome::Failed { error: e.report() });
+
+            let mut line = serde_json::to_string(&BulkResult { index, outcome })
+                .expect("bulk results serialize");
+            line.push('\n');
+
+            // The client went away, stop shrinking.
+            if lines.send(line).await.is_err() {
+                return;
+            }
+        }
+    }
 }
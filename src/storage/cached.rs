@@ -1,10 +1,14 @@
-use url::Url;
-
-use crate::{error, Code, Storage};
+use crate::{
+    error,
+    link::Link,
+    listing::{Page, Query},
+    lookup::Target,
+    Code, Storage,
+};
 
 pub trait Cache: Storage {
-    fn get(&self, code: &Code) -> Result<Url, error::Load>;
-    fn set(&self, url: &Url, code: &Code) -> Result<(), error::Storage>;
+    fn get(&self, code: &Code) -> Result<Link, error::Error>;
+    fn set(&self, link: &Link, code: &Code) -> Result<(), error::Error>;
 }
 
 pub struct Cached<C: Cache, S: Storage> {
@@ -13,19 +17,79 @@ pub struct Cached<C: Cache, S: Storage> {
 }
 
 impl<C: Cache, S: Storage> Storage for Cached<C, S> {
-    fn store(&mut self, url: Url, code: &Code) -> Result<(), error::Storage> {
-        self.storage.store(url, code)
+    fn store(&mut self, link: Link, code: &Code) -> Result<(), error::Error> {
+        self.storage.store(link, code)
+    }
+
+    /// Replaces the link in storage, and caches it as it is now.
+    fn replace(&mut self, link: Link, code: &Code) -> Result<(), error::Error> {
+        self.storage.replace(link.clone(), code)?;
+
+        if self.cache.set(&link, code).is_err() {
+            eprintln!("Failed to store URL in cache");
+        }
+
+        Ok(())
+    }
+
+    fn store_many(&mut self, links: Vec<(Link, Code)>) -> Vec<Result<(), error::Error>> {
+        self.storage.store_many(links)
+    }
+
+    /// Lists what's in storage, the cache only holds some of it.
+    fn list(&self, query: &Query) -> Result<Page, error::Error> {
+        self.storage.list(query)
     }
 
-    fn load(&self, code: &Code) -> Result<Url, error::Load> {
+    fn find(&self, target: &Target) -> Result<Vec<(Code, Link)>, error::Error> {
+        self.storage.find(target)
+    }
+
+    /// Retargets links in storage, and caches them as they are now so that
+    /// redirects follow right away.
+    fn retarget(&mut self, from: &str, to: &str) -> Result<Vec<(Code, Link)>, error::Error> {
+        let moved = self.storage.retarget(from, to)?;
+
+        for (code, link) in &moved {
+            if self.cache.set(link, code).is_err() {
+                eprintln!("Failed to store URL in cache");
+            }
+        }
+
+        Ok(moved)
+    }
+
+    fn load(&self, code: &Code) -> Result<Link, error::Error> {
         self.cache.load(code).or_else(|_| {
-            let url = self.storage.load(code)?;
+            let link = self.storage.load(code)?;
 
-            if self.cache.set(&url, code).is_err() {
+            if self.cache.set(&link, code).is_err() {
                 eprintln!("Failed to store URL in cache");
             }
 
-            Ok(url)
+            Ok(link)
         })
     }
+
+    /// Loads what's cached, and the rest from storage in one batch.
+    fn load_many(&self, codes: &[Code]) -> Vec<Result<Link, error::Error>> {
+        let mut results = self.cache.load_many(codes);
+
+        let missed: Vec<usize> = (0..codes.len()).filter(|&i| results[i].is_err()).collect();
+        let missed_codes: Vec<Code> = missed.iter().map(|&i| codes[i].clone()).collect();
+
+        for (i, loaded) in missed
+            .into_iter()
+            .zip(self.storage.load_many(&missed_codes))
+        {
+            if let Ok(link) = &loaded {
+                if self.cache.set(link, &codes[i]).is_err() {
+                    eprintln!("Failed to store URL in cache");
+                }
+            }
+            results[i] = loaded;
+        }
+
+        results
+    }
 }
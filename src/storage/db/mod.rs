@@ -1,2 +1,55 @@
 pub mod postgres;
 pub mod sqlite;
+
+use crate::error::CaseConflicts;
+
+/// Groups `(folded, code)` rows sorted by the folded code into conflicts.
+fn case_conflicts(rows: impl IntoIterator<Item = (String, String)>) -> Result<(), CaseConflicts> {
+    let mut groups: Vec<(String, Vec<String>)> = Vec::new();
+
+    for (folded, code) in rows {
+        match groups.last_mut() {
+            Some((last, codes)) if *last == folded => codes.push(code),
+            _ => groups.push((folded, vec![code])),
+        }
+    }
+
+    match groups.is_empty() {
+        true => Ok(()),
+        false => Err(CaseConflicts(
+            groups.into_iter().map(|(_, codes)| codes).collect(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(rows: &[(&str, &str)]) -> Vec<(String, String)> {
+        rows.iter()
+            .map(|(folded, code)| (folded.to_string(), code.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn no_rows_no_conflicts() {
+        assert!(case_conflicts(Vec::new()).is_ok());
+    }
+
+    #[test]
+    fn groups_by_folded_code() {
+        let conflicts = case_conflicts(rows(&[
+            ("blaze", "Blaze"),
+            ("blaze", "blaze"),
+            ("otter", "OTTER"),
+            ("otter", "Otter"),
+        ]))
+        .unwrap_err();
+
+        assert_eq!(
+            conflicts.0,
+            vec![vec!["Blaze", "blaze"], vec!["OTTER", "Otter"]]
+        );
+    }
+}
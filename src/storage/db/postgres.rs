@@ -1,14 +1,35 @@
+use crate::{
+    error,
+    link::{self, Link, Meta},
+    listing::{Page, Query},
+    lookup::{self, Target},
+    Code, Reserve, Storage,
+};
 use r2d2::Pool;
 use r2d2_postgres::{
-    postgres::{Config, NoTls},
+    postgres::{self, types::ToSql, Config, GenericClient, NoTls, Row, ToStatement},
     PostgresConnectionManager,
 };
+use std::collections::{HashMap, HashSet};
 use tokio::task::block_in_place;
 use url::Url;
 
-use crate::{error, Code, Storage};
+/// Columns set by `insert.sql`.
+const COLUMNS: usize = 9;
+
+/// Links inserted per statement, keeping clear of the limit of 65535
+/// parameters.
+const ROWS_PER_INSERT: usize = 1000;
+
+/// Database used when the connection string doesn't name one.
+const DEFAULT_DBNAME: &str = "hackathon_raptors";
 
-pub struct Postgres(Pool<PostgresConnectionManager<NoTls>>);
+#[derive(Clone)]
+pub struct Postgres {
+    pool: Pool<PostgresConnectionManager<NoTls>>,
+    /// Whether codes are looked up ignoring case.
+    folded: bool,
+}
 
 impl Postgres {
     pub async fn connect(config: &str) -> Result<Self, Box<dyn std::error::Error>> {
@@ -16,54 +37,413 @@ impl Postgres {
         // The synchronous implementation of postgres client depends on `tokio`
         // but all it does is some `block_on` wrapping on all calls.
         block_in_place(move || {
-            let config = config
-                .parse::<Config>()?
-                .dbname("hackathon_raptors")
-                .to_owned();
+            let config = config.parse::<Config>().map(with_default_dbname)?;
 
             let manager = PostgresConnectionManager::new(config, NoTls);
             // XXX: This may not fail on `connect`, but on read or write.
             let pool = Pool::new(manager)?;
 
-            pool.get()?
-                .batch_execute(include_str!("scripts/schema.sql"))?;
+            let mut conn = pool.get()?;
+            conn.batch_execute(include_str!("scripts/schema.sql"))?;
+            conn.batch_execute(include_str!("scripts/postgres/meta.sql"))?;
+            conn.batch_execute(include_str!("scripts/postgres/reverse.sql"))?;
+            key_domains(&mut *conn)?;
+            drop(conn);
+
+            Ok(Self {
+                pool,
+                folded: false,
+            })
+        })
+    }
+
+    /// Switches to case-insensitive codes, adding a unique index on their
+    /// lowercased form.
+    ///
+    /// Fails with `error::CaseConflicts` if stored codes differ only by
+    /// case, leaving the database as it was.
+    pub fn case_insensitive(self) -> Result<Self, Box<dyn std::error::Error>> {
+        block_in_place(move || {
+            let mut conn = self.pool.get()?;
+            let mut tx = conn.transaction()?;
+
+            let rows = tx
+                .query(include_str!("scripts/postgres/conflicts.sql"), &[])?
+                .iter()
+                .map(|row| (row.get(0), row.get(1)))
+                .collect::<Vec<_>>();
+            super::case_conflicts(rows)?;
+
+            tx.batch_execute(include_str!("scripts/postgres/fold.sql"))?;
+            tx.commit()?;
+            drop(conn);
 
-            Ok(Self(pool))
+            Ok(Self {
+                folded: true,
+                ..self
+            })
         })
     }
 }
 
+/// Placeholders for `rows` rows of `insert.sql`'s columns.
+fn values(rows: usize) -> String {
+    (0..rows)
+        .map(|row| {
+            let params: Vec<String> = (1..=COLUMNS)
+                .map(|column| format!("${}", row * COLUMNS + column))
+                .collect();
+            format!("({})", params.join(", "))
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Reads a link from the columns of `select.sql`, starting at `from`.
+fn link(row: &Row, from: usize) -> Option<Link> {
+    Some(Link {
+        url: row.get::<_, &str>(from).parse().ok()?,
+        created_at: row.get(from + 1),
+        updated_at: row.get(from + 2),
+        meta: Meta {
+            owner: row.get(from + 3),
+            title: row.get(from + 4),
+            tags: row.get(from + 5),
+            notes: row.get(from + 6),
+        },
+    })
+}
+
+/// Sets the domain key of links stored before links were looked up by
+/// domain.
+fn key_domains(client: &mut impl GenericClient) -> Result<(), postgres::Error> {
+    let unkeyed = client.query(include_str!("scripts/postgres/unkeyed.sql"), &[])?;
+
+    let mut tx = client.transaction()?;
+    let stmt = tx.prepare(include_str!("scripts/postgres/key.sql"))?;
+
+    for row in unkeyed {
+        let Some(key) = Url::parse(row.get(1))
+            .ok()
+            .as_ref()
+            .and_then(lookup::url_key)
+        else {
+            continue;
+        };
+        tx.execute(&stmt, &[&row.get::<_, &str>(0), &key])?;
+    }
+
+    tx.commit()
+}
+
+/// Links pointing at `target`, using the indexes on `url` and `domain`.
+fn find(
+    client: &mut impl GenericClient,
+    target: &Target,
+) -> Result<Vec<(Code, Link)>, postgres::Error> {
+    let rows = match target {
+        Target::Url(url) => client.query(
+            include_str!("scripts/postgres/find_url.sql"),
+            &[&url.as_str()],
+        )?,
+        Target::Host(host) => client.query(
+            include_str!("scripts/postgres/find_host.sql"),
+            &[&lookup::domain_key(host)],
+        )?,
+        Target::Domain(domain) => {
+            let (lower, upper) = lookup::domain_range(domain);
+            client.query(
+                include_str!("scripts/postgres/find_domain.sql"),
+                &[&lower, &upper],
+            )?
+        }
+    };
+
+    Ok(rows
+        .iter()
+        .filter_map(|row| Some((Code::new(row.get(0)), link(row, 1)?)))
+        .collect())
+}
+
+/// Inserts a link with `insert.sql` or a statement taking the same
+/// parameters.
+fn insert(
+    client: &mut impl GenericClient,
+    query: &(impl ToStatement + ?Sized),
+    link: &Link,
+    code: &Code,
+) -> Result<u64, postgres::Error> {
+    client.execute(
+        query,
+        &[
+            &code.as_str(),
+            &link.url.as_str(),
+            &link.created_at,
+            &link.updated_at,
+            &link.meta.owner,
+            &link.meta.title,
+            &link.meta.tags,
+            &link.meta.notes,
+            &lookup::url_key(&link.url),
+        ],
+    )
+}
+
 impl Storage for Postgres {
-    fn store(&mut self, url: Url, code: &Code) -> Result<(), error::Storage> {
+    fn store(&mut self, link: Link, code: &Code) -> Result<(), error::Error> {
         block_in_place(move || {
-            self.0
-                .get()
-                .map_err(|e| error::Storage::Internal(e.to_string()))?
-                .execute(
-                    include_str!("scripts/postgres/insert.sql"),
-                    &[&code.as_str(), &url.to_string()],
-                )?;
+            insert(
+                &mut *self.pool.get()?,
+                include_str!("scripts/postgres/insert.sql"),
+                &link,
+                code,
+            )?;
 
             Ok(())
         })
     }
 
-    fn load(&self, code: &Code) -> Result<Url, error::Load> {
+    fn replace(&mut self, link: Link, code: &Code) -> Result<(), error::Error> {
         block_in_place(move || {
-            let mut conn = self
-                .0
-                .get()
-                .map_err(|e| error::Load::Internal(e.to_string()))?;
-
-            conn.query(
-                include_str!("scripts/postgres/select.sql"),
-                &[&code.as_str()],
-            )
-            .map_err(|e| error::Load::Internal(e.to_string()))?
-            .iter()
-            .filter_map(|row| row.get::<usize, String>(0).parse::<Url>().ok())
-            .next()
-            .ok_or(error::Load::NotFound)
+            insert(
+                &mut *self.pool.get()?,
+                include_str!("scripts/postgres/replace.sql"),
+                &link,
+                code,
+            )?;
+
+            Ok(())
         })
     }
+
+    /// Inserts all links in one transaction with multi-row inserts, skipping
+    /// used codes instead of failing on them.
+    fn store_many(&mut self, links: Vec<(Link, Code)>) -> Vec<Result<(), error::Error>> {
+        let count = links.len();
+
+        let stored = || -> Result<Vec<Result<(), error::Error>>, error::Error> {
+            let mut conn = self.pool.get()?;
+            let mut tx = conn.transaction()?;
+
+            let mut results = Vec::with_capacity(count);
+            for chunk in links.chunks(ROWS_PER_INSERT) {
+                let text: Vec<(&str, &str, Option<String>)> = chunk
+                    .iter()
+                    .map(|(link, code)| {
+                        (code.as_str(), link.url.as_str(), lookup::url_key(&link.url))
+                    })
+                    .collect();
+
+                let mut params: Vec<&(dyn ToSql + Sync)> =
+                    Vec::with_capacity(chunk.len() * COLUMNS);
+                for ((link, _), (code, url, domain)) in chunk.iter().zip(&text) {
+                    params.extend_from_slice(&[
+                        code,
+                        url,
+                        &link.created_at,
+                        &link.updated_at,
+                        &link.meta.owner,
+                        &link.meta.title,
+                        &link.meta.tags,
+                        &link.meta.notes,
+                        domain,
+                    ]);
+                }
+
+                let insert = format!(
+                    "{} {} ON CONFLICT DO NOTHING RETURNING code",
+                    include_str!("scripts/postgres/insert_many.sql").trim_end(),
+                    values(chunk.len())
+                );
+
+                // Codes used twice in a chunk are only inserted once.
+                let mut inserted: HashSet<String> = tx
+                    .query(&insert, &params)?
+                    .iter()
+                    .map(|row| row.get(0))
+                    .collect();
+
+                results.extend(chunk.iter().map(
+                    |(_, code)| match inserted.remove(code.as_str()) {
+                        true => Ok(()),
+                        false => Err(error::Error::Duplicate),
+                    },
+                ));
+            }
+
+            tx.commit()?;
+
+            Ok(results)
+        };
+
+        block_in_place(|| stored().unwrap_or_else(|e| vec![Err(e); count]))
+    }
+
+    fn load(&self, code: &Code) -> Result<Link, error::Error> {
+        block_in_place(move || {
+            let mut conn = self.pool.get()?;
+
+            let select = match self.folded {
+                true => include_str!("scripts/postgres/select_folded.sql"),
+                false => include_str!("scripts/postgres/select.sql"),
+            };
+
+            conn.query(select, &[&code.as_str()])?
+                .iter()
+                .filter_map(|row| link(row, 0))
+                .next()
+                .ok_or(error::Error::NotFound)
+        })
+    }
+
+    fn list(&self, query: &Query) -> Result<Page, error::Error> {
+        block_in_place(move || {
+            let mut conn = self.pool.get()?;
+
+            let links = conn
+                .query(
+                    include_str!("scripts/postgres/list.sql"),
+                    &[
+                        &query.cursor,
+                        &query.owner,
+                        &query.tag,
+                        &query.created_after,
+                        &query.created_before,
+                        &query.search_folded(),
+                        &(query.limit() as i64 + 1),
+                    ],
+                )?
+                .iter()
+                .filter_map(|row| Some((Code::new(row.get(0)), link(row, 1)?)))
+                .collect();
+
+            Ok(Page::ordered(links, query.limit()))
+        })
+    }
+
+    fn find(&self, target: &Target) -> Result<Vec<(Code, Link)>, error::Error> {
+        block_in_place(move || Ok(find(&mut *self.pool.get()?, target)?))
+    }
+
+    fn retarget(&mut self, from: &str, to: &str) -> Result<Vec<(Code, Link)>, error::Error> {
+        block_in_place(move || {
+            let mut conn = self.pool.get()?;
+            let mut tx = conn.transaction()?;
+
+            let mut moved = find(&mut tx, &Target::Domain(from.to_string()))?;
+            let stmt = tx.prepare(include_str!("scripts/postgres/retarget.sql"))?;
+            let now = link::now();
+
+            for (code, link) in &mut moved {
+                link.url =
+                    lookup::retarget(&link.url, from, to).expect("found links are on `from`");
+                link.updated_at = now;
+                tx.execute(
+                    &stmt,
+                    &[
+                        &code.as_str(),
+                        &link.url.as_str(),
+                        &lookup::url_key(&link.url),
+                        &now,
+                    ],
+                )?;
+            }
+
+            tx.commit()?;
+
+            Ok(moved)
+        })
+    }
+
+    /// Selects all links with one query.
+    fn load_many(&self, codes: &[Code]) -> Vec<Result<Link, error::Error>> {
+        let loaded = || -> Result<Vec<Result<Link, error::Error>>, error::Error> {
+            let mut conn = self.pool.get()?;
+
+            let select = match self.folded {
+                true => include_str!("scripts/postgres/select_many_folded.sql"),
+                false => include_str!("scripts/postgres/select_many.sql"),
+            };
+
+            let wanted: Vec<&str> = codes.iter().map(Code::as_str).collect();
+
+            let links: HashMap<String, Link> = conn
+                .query(select, &[&wanted])?
+                .iter()
+                .filter_map(|row| Some((row.get(0), link(row, 1)?)))
+                .collect();
+
+            Ok(codes
+                .iter()
+                .map(|code| {
+                    links
+                        .get(code.as_str())
+                        .cloned()
+                        .ok_or(error::Error::NotFound)
+                })
+                .collect())
+        };
+
+        block_in_place(|| loaded().unwrap_or_else(|e| vec![Err(e); codes.len()]))
+    }
+}
+
+impl Reserve for Postgres {
+    fn reserve(&mut self, codes: Vec<Code>) -> Result<Vec<Code>, error::Error> {
+        block_in_place(move || {
+            let mut conn = self.pool.get()?;
+
+            let reserve = match self.folded {
+                true => include_str!("scripts/postgres/reserve_folded.sql"),
+                false => include_str!("scripts/postgres/reserve.sql"),
+            };
+
+            let mut tx = conn.transaction()?;
+            let stmt = tx.prepare(reserve)?;
+            let mut reserved = Vec::with_capacity(codes.len());
+
+            for code in codes {
+                if tx.execute(&stmt, &[&code.as_str()])? == 1 {
+                    reserved.push(code);
+                }
+            }
+
+            tx.commit()?;
+
+            Ok(reserved)
+        })
+    }
+}
+
+fn with_default_dbname(mut config: Config) -> Config {
+    if config.get_dbname().is_none() {
+        config.dbname(DEFAULT_DBNAME);
+    }
+    config
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_are_numbered_by_row() {
+        assert_eq!(values(1), "($1, $2, $3, $4, $5, $6, $7, $8, $9)");
+        assert!(values(2).ends_with("($10, $11, $12, $13, $14, $15, $16, $17, $18)"));
+    }
+
+    #[test]
+    fn named_databases_are_kept() {
+        let dbname = |config: &str| {
+            with_default_dbname(config.parse().unwrap())
+                .get_dbname()
+                .map(str::to_string)
+        };
+
+        assert_eq!(dbname("postgres://localhost/mydb").as_deref(), Some("mydb"));
+        assert_eq!(
+            dbname("postgres://localhost").as_deref(),
+            Some(DEFAULT_DBNAME)
+        );
+    }
 }
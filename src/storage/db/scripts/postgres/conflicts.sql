@@ -0,0 +1,5 @@
+SELECT lower(code), code FROM urls
+WHERE lower(code) IN (
+  SELECT lower(code) FROM urls GROUP BY lower(code) HAVING count(*) > 1
+)
+ORDER BY 1, 2;
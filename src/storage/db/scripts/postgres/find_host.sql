@@ -0,0 +1,3 @@
+SELECT code, url, created_at, updated_at, owner, title, tags, notes
+FROM urls WHERE domain = $1
+ORDER BY code;
@@ -0,0 +1,3 @@
+SELECT code, url, created_at, updated_at, owner, title, tags, notes
+FROM urls WHERE url = $1
+ORDER BY code;
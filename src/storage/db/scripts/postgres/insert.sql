@@ -1 +1,2 @@
-INSERT INTO urls (code, url) VALUES ($1, $2);
+INSERT INTO urls (code, url, created_at, updated_at, owner, title, tags, notes, domain)
+VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9);
@@ -0,0 +1,2 @@
+INSERT INTO urls (code, url, created_at, updated_at, owner, title, tags, notes, domain)
+VALUES
@@ -0,0 +1 @@
+UPDATE urls SET domain = $2 WHERE code = $1;
@@ -0,0 +1,10 @@
+SELECT code, url, created_at, updated_at, owner, title, tags, notes
+FROM urls
+WHERE ($1::TEXT IS NULL OR code > $1)
+  AND ($2::TEXT IS NULL OR owner = $2)
+  AND ($3::TEXT IS NULL OR $3 = ANY (tags))
+  AND ($4::BIGINT IS NULL OR created_at >= $4)
+  AND ($5::BIGINT IS NULL OR created_at < $5)
+  AND ($6::TEXT IS NULL OR strpos(lower(url), $6) > 0 OR strpos(lower(code), $6) > 0)
+ORDER BY code
+LIMIT $7;
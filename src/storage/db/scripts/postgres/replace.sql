@@ -0,0 +1,11 @@
+INSERT INTO urls (code, url, created_at, updated_at, owner, title, tags, notes, domain)
+VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+ON CONFLICT (code) DO UPDATE SET
+  url = excluded.url,
+  created_at = excluded.created_at,
+  updated_at = excluded.updated_at,
+  owner = excluded.owner,
+  title = excluded.title,
+  tags = excluded.tags,
+  notes = excluded.notes,
+  domain = excluded.domain;
@@ -0,0 +1,3 @@
+INSERT INTO keys (code)
+SELECT $1 WHERE NOT EXISTS (SELECT 1 FROM urls WHERE code = $1)
+ON CONFLICT DO NOTHING;
@@ -0,0 +1,2 @@
+UPDATE urls SET url = $2, domain = $3, updated_at = $4
+WHERE code = $1;
@@ -0,0 +1,3 @@
+ALTER TABLE urls ADD COLUMN IF NOT EXISTS domain TEXT COLLATE "C";
+CREATE INDEX IF NOT EXISTS urls_domain ON urls (domain);
+CREATE INDEX IF NOT EXISTS urls_url ON urls (url);
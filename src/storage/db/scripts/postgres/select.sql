@@ -1 +1,2 @@
-SELECT url FROM urls WHERE code = $1;
+SELECT url, created_at, updated_at, owner, title, tags, notes
+FROM urls WHERE code = $1;
@@ -0,0 +1,2 @@
+SELECT url, created_at, updated_at, owner, title, tags, notes
+FROM urls WHERE lower(code) = lower($1);
@@ -0,0 +1,3 @@
+SELECT wanted.code, url, created_at, updated_at, owner, title, tags, notes
+FROM unnest($1::TEXT[]) AS wanted (code)
+JOIN urls ON lower(urls.code) = lower(wanted.code);
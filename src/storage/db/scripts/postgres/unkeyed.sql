@@ -0,0 +1 @@
+SELECT code, url FROM urls WHERE domain IS NULL;
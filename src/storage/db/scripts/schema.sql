@@ -2,3 +2,7 @@ CREATE TABLE IF NOT EXISTS urls (
   code TEXT PRIMARY KEY,
   url TEXT NOT NULL
 );
+
+CREATE TABLE IF NOT EXISTS keys (
+  code TEXT PRIMARY KEY
+);
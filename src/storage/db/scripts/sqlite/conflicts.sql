@@ -0,0 +1,5 @@
+SELECT fold(`code`), `code` FROM `urls`
+WHERE fold(`code`) IN (
+  SELECT fold(`code`) FROM `urls` GROUP BY fold(`code`) HAVING count(*) > 1
+)
+ORDER BY 1, 2;
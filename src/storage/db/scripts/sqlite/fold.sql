@@ -0,0 +1 @@
+CREATE UNIQUE INDEX IF NOT EXISTS `urls_code_folded` ON `urls` (fold(`code`));
@@ -0,0 +1,10 @@
+SELECT code, url, created_at, updated_at, owner, title, tags, notes
+FROM urls
+WHERE (?1 IS NULL OR code > ?1)
+  AND (?2 IS NULL OR owner = ?2)
+  AND (?3 IS NULL OR EXISTS (SELECT 1 FROM json_each(urls.tags) WHERE value = ?3))
+  AND (?4 IS NULL OR created_at >= ?4)
+  AND (?5 IS NULL OR created_at < ?5)
+  AND (?6 IS NULL OR instr(fold(url), ?6) > 0 OR instr(fold(code), ?6) > 0)
+ORDER BY code
+LIMIT ?7;
@@ -0,0 +1,6 @@
+ALTER TABLE `urls` ADD COLUMN `created_at` INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE `urls` ADD COLUMN `updated_at` INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE `urls` ADD COLUMN `owner` TEXT;
+ALTER TABLE `urls` ADD COLUMN `title` TEXT;
+ALTER TABLE `urls` ADD COLUMN `tags` TEXT NOT NULL DEFAULT '[]';
+ALTER TABLE `urls` ADD COLUMN `notes` TEXT;
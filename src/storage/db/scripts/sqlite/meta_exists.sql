@@ -0,0 +1 @@
+SELECT 1 FROM pragma_table_info('urls') WHERE name = 'created_at';
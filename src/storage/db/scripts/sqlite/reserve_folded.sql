@@ -0,0 +1,2 @@
+INSERT OR IGNORE INTO `keys` (`code`)
+SELECT ?1 WHERE NOT EXISTS (SELECT 1 FROM `urls` WHERE fold(`code`) = fold(?1));
@@ -0,0 +1,2 @@
+UPDATE urls SET url = ?2, domain = domain_key(?2), updated_at = ?3
+WHERE code = ?1;
@@ -0,0 +1,4 @@
+ALTER TABLE `urls` ADD COLUMN `domain` TEXT;
+UPDATE `urls` SET `domain` = domain_key(`url`);
+CREATE INDEX IF NOT EXISTS `urls_domain` ON `urls` (`domain`);
+CREATE INDEX IF NOT EXISTS `urls_url` ON `urls` (`url`);
@@ -1,25 +1,162 @@
 use r2d2::Pool;
 use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{functions::FunctionFlags, Connection, Row, Statement};
 use std::error::Error;
+
+use crate::{
+    error,
+    link::{self, Link, Meta},
+    listing::{Page, Query},
+    lookup::{self, Target},
+    Code, Reserve, Storage,
+};
 use url::Url;
 
-use crate::{error, Code, Storage};
+#[derive(Clone)]
+pub struct Sqlite {
+    pool: Pool<SqliteConnectionManager>,
+    /// Whether codes are looked up ignoring case.
+    folded: bool,
+}
+
+/// Registers `fold`, which SQLite's ASCII-only `lower` can't stand in for,
+/// and `domain_key`, the `lookup::url_key` of a URL.
+fn register_functions(conn: &mut Connection) -> rusqlite::Result<()> {
+    let flags = FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC;
+
+    conn.create_scalar_function("fold", 1, flags, |ctx| {
+        Ok(ctx.get::<String>(0)?.to_lowercase())
+    })?;
+    conn.create_scalar_function("domain_key", 1, flags, |ctx| {
+        Ok(Url::parse(&ctx.get::<String>(0)?)
+            .ok()
+            .and_then(|url| lookup::url_key(&url)))
+    })
+}
+
+/// Reads a link from the columns of `select.sql`, starting at `from`.
+fn link(row: &Row, from: usize) -> rusqlite::Result<Link> {
+    let invalid =
+        |i, e| rusqlite::Error::FromSqlConversionFailure(i, rusqlite::types::Type::Text, e);
+
+    Ok(Link {
+        url: row
+            .get::<_, String>(from)?
+            .parse()
+            .map_err(|e| invalid(from, Box::new(e)))?,
+        created_at: row.get(from + 1)?,
+        updated_at: row.get(from + 2)?,
+        meta: Meta {
+            owner: row.get(from + 3)?,
+            title: row.get(from + 4)?,
+            tags: serde_json::from_str(&row.get::<_, String>(from + 5)?)
+                .map_err(|e| invalid(from + 5, Box::new(e)))?,
+            notes: row.get(from + 6)?,
+        },
+    })
+}
+
+/// Inserts a link with a statement prepared from `insert.sql`, or one
+/// taking the same parameters.
+fn insert(stmt: &mut Statement, link: &Link, code: &Code) -> Result<(), error::Error> {
+    let tags = serde_json::to_string(&link.meta.tags)
+        .map_err(|e| error::Error::Internal(e.to_string()))?;
+
+    stmt.execute((
+        code.as_str(),
+        link.url.as_str(),
+        link.created_at,
+        link.updated_at,
+        &link.meta.owner,
+        &link.meta.title,
+        tags,
+        &link.meta.notes,
+    ))?;
+
+    Ok(())
+}
+
+/// Links pointing at `target`, using the indexes on `url` and `domain`.
+fn find(conn: &Connection, target: &Target) -> Result<Vec<(Code, Link)>, error::Error> {
+    let row = |row: &Row| Ok((Code::new(row.get(0)?), link(row, 1)?));
+
+    let links = match target {
+        Target::Url(url) => conn
+            .prepare_cached(include_str!("scripts/sqlite/find_url.sql"))?
+            .query_map([url.as_str()], row)?
+            .collect::<Result<_, _>>()?,
+        Target::Host(host) => conn
+            .prepare_cached(include_str!("scripts/sqlite/find_host.sql"))?
+            .query_map([lookup::domain_key(host)], row)?
+            .collect::<Result<_, _>>()?,
+        Target::Domain(domain) => conn
+            .prepare_cached(include_str!("scripts/sqlite/find_domain.sql"))?
+            .query_map(lookup::domain_range(domain), row)?
+            .collect::<Result<_, _>>()?,
+    };
 
-pub struct Sqlite(Pool<SqliteConnectionManager>);
+    Ok(links)
+}
 
 impl Sqlite {
     fn with_pool(manager: SqliteConnectionManager) -> Result<Self, Box<dyn Error>> {
-        let pool = Pool::new(manager)?;
+        let pool = Pool::new(manager.with_init(register_functions))?;
+        let conn = pool.get()?;
+
+        conn.execute_batch(include_str!("scripts/schema.sql"))?;
 
-        pool.get()?
-            .execute(include_str!("scripts/schema.sql"), ())?;
+        // Databases from before links had metadata get the columns added.
+        if !conn
+            .prepare(include_str!("scripts/sqlite/meta_exists.sql"))?
+            .exists([])?
+        {
+            conn.execute_batch(include_str!("scripts/sqlite/meta.sql"))?;
+        }
 
-        Ok(Self(pool))
+        // As are the domains links are looked up by.
+        if !conn
+            .prepare(include_str!("scripts/sqlite/reverse_exists.sql"))?
+            .exists([])?
+        {
+            conn.execute_batch(include_str!("scripts/sqlite/reverse.sql"))?;
+        }
+
+        drop(conn);
+
+        Ok(Self {
+            pool,
+            folded: false,
+        })
     }
 
     pub fn open(path: &str) -> Result<Self, Box<dyn Error>> {
         Self::with_pool(SqliteConnectionManager::file(path))
     }
+
+    /// Switches to case-insensitive codes, adding a unique index on their
+    /// case-folded form.
+    ///
+    /// Fails with `error::CaseConflicts` if stored codes differ only by
+    /// case, leaving the database as it was.
+    pub fn case_insensitive(self) -> Result<Self, Box<dyn Error>> {
+        let mut conn = self.pool.get()?;
+        let tx = conn.transaction()?;
+
+        let rows = tx
+            .prepare(include_str!("scripts/sqlite/conflicts.sql"))?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        super::case_conflicts(rows)?;
+
+        tx.execute_batch(include_str!("scripts/sqlite/fold.sql"))?;
+        tx.commit()?;
+        drop(conn);
+
+        Ok(Self {
+            folded: true,
+            ..self
+        })
+    }
 }
 
 impl Default for Sqlite {
@@ -29,38 +166,159 @@ impl Default for Sqlite {
 }
 
 impl Storage for Sqlite {
-    fn store(&mut self, url: Url, code: &Code) -> Result<(), error::Storage> {
-        self.0
-            .get()
-            .map_err(|e| error::Storage::Internal(e.to_string()))?
-            .execute(
-                include_str!("scripts/sqlite/insert.sql"),
-                (code.as_str(), url.as_str()),
-            )?;
-
-        Ok(())
+    fn store(&mut self, link: Link, code: &Code) -> Result<(), error::Error> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare_cached(include_str!("scripts/sqlite/insert.sql"))?;
+
+        insert(&mut stmt, &link, code)
+    }
+
+    fn replace(&mut self, link: Link, code: &Code) -> Result<(), error::Error> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare_cached(include_str!("scripts/sqlite/replace.sql"))?;
+
+        insert(&mut stmt, &link, code)
+    }
+
+    fn load(&self, code: &Code) -> Result<Link, error::Error> {
+        let conn = self.pool.get()?;
+
+        let select = match self.folded {
+            true => include_str!("scripts/sqlite/select_folded.sql"),
+            false => include_str!("scripts/sqlite/select.sql"),
+        };
+
+        let mut stmt = conn.prepare(select)?;
+
+        let mut links = stmt.query_map([code.as_str()], |row| link(row, 0))?;
+
+        links
+            .next()
+            .ok_or(error::Error::NotFound)?
+            .map_err(error::Error::from)
+    }
+
+    /// Loads all links over one connection, with one prepared statement.
+    fn load_many(&self, codes: &[Code]) -> Vec<Result<Link, error::Error>> {
+        let loaded = || -> Result<Vec<Result<Link, error::Error>>, error::Error> {
+            let conn = self.pool.get()?;
+
+            let select = match self.folded {
+                true => include_str!("scripts/sqlite/select_folded.sql"),
+                false => include_str!("scripts/sqlite/select.sql"),
+            };
+
+            let mut stmt = conn.prepare_cached(select)?;
+
+            Ok(codes
+                .iter()
+                .map(|code| {
+                    stmt.query_row([code.as_str()], |row| link(row, 0))
+                        .map_err(|e| match e {
+                            rusqlite::Error::QueryReturnedNoRows => error::Error::NotFound,
+                            e => e.into(),
+                        })
+                })
+                .collect())
+        };
+
+        loaded().unwrap_or_else(|e| vec![Err(e); codes.len()])
+    }
+
+    fn list(&self, query: &Query) -> Result<Page, error::Error> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare_cached(include_str!("scripts/sqlite/list.sql"))?;
+
+        let links = stmt
+            .query_map(
+                (
+                    &query.cursor,
+                    &query.owner,
+                    &query.tag,
+                    query.created_after,
+                    query.created_before,
+                    query.search_folded(),
+                    query.limit() + 1,
+                ),
+                |row| Ok((Code::new(row.get(0)?), link(row, 1)?)),
+            )?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Page::ordered(links, query.limit()))
+    }
+
+    fn find(&self, target: &Target) -> Result<Vec<(Code, Link)>, error::Error> {
+        find(&*self.pool.get()?, target)
+    }
+
+    fn retarget(&mut self, from: &str, to: &str) -> Result<Vec<(Code, Link)>, error::Error> {
+        let mut conn = self.pool.get()?;
+        let tx = conn.transaction()?;
+
+        let mut moved = find(&tx, &Target::Domain(from.to_string()))?;
+        let now = link::now();
+
+        {
+            let mut stmt = tx.prepare(include_str!("scripts/sqlite/retarget.sql"))?;
+            for (code, link) in &mut moved {
+                link.url =
+                    lookup::retarget(&link.url, from, to).expect("found links are on `from`");
+                link.updated_at = now;
+                stmt.execute((code.as_str(), link.url.as_str(), now))?;
+            }
+        }
+
+        tx.commit()?;
+
+        Ok(moved)
+    }
+
+    /// Inserts all links in one transaction. Links that fail don't keep the
+    /// others from being stored.
+    fn store_many(&mut self, links: Vec<(Link, Code)>) -> Vec<Result<(), error::Error>> {
+        let count = links.len();
+
+        let stored = || -> Result<Vec<Result<(), error::Error>>, error::Error> {
+            let mut conn = self.pool.get()?;
+            let tx = conn.transaction()?;
+
+            let results = {
+                let mut stmt = tx.prepare(include_str!("scripts/sqlite/insert.sql"))?;
+                links
+                    .iter()
+                    .map(|(link, code)| insert(&mut stmt, link, code))
+                    .collect()
+            };
+
+            tx.commit()?;
+
+            Ok(results)
+        };
+
+        stored().unwrap_or_else(|e| vec![Err(e); count])
     }
+}
+
+impl Reserve for Sqlite {
+    fn reserve(&mut self, codes: Vec<Code>) -> Result<Vec<Code>, error::Error> {
+        let mut conn = self.pool.get()?;
+
+        let reserve = match self.folded {
+            true => include_str!("scripts/sqlite/reserve_folded.sql"),
+            false => include_str!("scripts/sqlite/reserve.sql"),
+        };
+
+        let tx = conn.transaction()?;
+        let mut reserved = Vec::with_capacity(codes.len());
+
+        for code in codes {
+            if tx.execute(reserve, [code.as_str()])? == 1 {
+                reserved.push(code);
+            }
+        }
+
+        tx.commit()?;
 
-    fn load(&self, code: &Code) -> Result<Url, error::Load> {
-        let conn = self
-            .0
-            .get()
-            .map_err(|e| error::Load::Internal(e.to_string()))?; // FIXME
-
-        let mut stmt = conn
-            .prepare(include_str!("scripts/sqlite/select.sql"))
-            .map_err(|e| error::Load::Internal(e.to_string()))?;
-
-        let mut urls = stmt
-            .query_map([code.as_str()], |row| {
-                row.get::<usize, String>(0)?
-                    .parse()
-                    .map_err(|_| rusqlite::Error::InvalidQuery)
-            })
-            .map_err(|e| error::Load::Internal(e.to_string()))?;
-
-        urls.next()
-            .ok_or(error::Load::NotFound)?
-            .map_err(|e| error::Load::Internal(e.to_string()))
+        Ok(reserved)
     }
 }
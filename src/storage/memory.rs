@@ -1,20 +1,82 @@
-use std::collections::HashMap;
-use url::Url;
+use std::collections::{hash_map::Entry, HashMap};
 
-use crate::{error, Code, Storage};
+use crate::{
+    error,
+    link::{self, Link},
+    listing::{Page, Query},
+    lookup::{self, Target},
+    Code, Storage,
+};
 
 #[derive(Default)]
-pub struct Memory(HashMap<Code, Url>);
+pub struct Memory(HashMap<Code, Link>);
 
 impl Storage for Memory {
-    fn store(&mut self, url: Url, code: &Code) -> Result<(), error::Storage> {
-        match self.0.insert(code.clone(), url) {
-            Some(_) => Err(error::Storage::Duplicate),
-            None => Ok(()),
+    fn store(&mut self, link: Link, code: &Code) -> Result<(), error::Error> {
+        match self.0.entry(code.clone()) {
+            Entry::Occupied(_) => Err(error::Error::Duplicate),
+            Entry::Vacant(entry) => {
+                entry.insert(link);
+                Ok(())
+            }
         }
     }
 
-    fn load(&self, code: &Code) -> Result<Url, error::Load> {
-        self.0.get(code).cloned().ok_or(error::Load::NotFound)
+    fn replace(&mut self, link: Link, code: &Code) -> Result<(), error::Error> {
+        self.0.insert(code.clone(), link);
+        Ok(())
+    }
+
+    fn load(&self, code: &Code) -> Result<Link, error::Error> {
+        self.0.get(code).cloned().ok_or(error::Error::NotFound)
+    }
+
+    fn list(&self, query: &Query) -> Result<Page, error::Error> {
+        let mut links: Vec<(Code, Link)> = self
+            .0
+            .iter()
+            .filter(|(code, link)| {
+                query
+                    .cursor
+                    .as_deref()
+                    .is_none_or(|cursor| code.as_str() > cursor)
+                    && query.matches(code, link)
+            })
+            .map(|(code, link)| (code.clone(), link.clone()))
+            .collect();
+
+        links.sort_by(|(a, _), (b, _)| a.as_str().cmp(b.as_str()));
+        links.truncate(query.limit() + 1);
+
+        Ok(Page::ordered(links, query.limit()))
+    }
+
+    fn find(&self, target: &Target) -> Result<Vec<(Code, Link)>, error::Error> {
+        let mut links: Vec<(Code, Link)> = self
+            .0
+            .iter()
+            .filter(|(_, link)| target.matches(&link.url))
+            .map(|(code, link)| (code.clone(), link.clone()))
+            .collect();
+
+        links.sort_by(|(a, _), (b, _)| a.as_str().cmp(b.as_str()));
+
+        Ok(links)
+    }
+
+    fn retarget(&mut self, from: &str, to: &str) -> Result<Vec<(Code, Link)>, error::Error> {
+        let mut moved = Vec::new();
+
+        for (code, link) in self.0.iter_mut() {
+            if let Some(url) = lookup::retarget(&link.url, from, to) {
+                link.url = url;
+                link.updated_at = link::now();
+                moved.push((code.clone(), link.clone()));
+            }
+        }
+
+        moved.sort_by(|(a, _), (b, _)| a.as_str().cmp(b.as_str()));
+
+        Ok(moved)
     }
 }
@@ -1,12 +1,76 @@
 use r2d2::Pool;
-use redis::{Client, Commands};
+use redis::{Client, Commands, Connection};
 use std::time::Duration;
 use url::Url;
 
-use crate::{error, Code, Storage};
+use crate::{
+    error,
+    link::{self, Link, Meta},
+    listing::{Page, Query},
+    lookup::{self, Target},
+    Code, Storage,
+};
 
 use super::Cache;
 
+/// Keys scanned at a time when looking up links.
+const SCAN_COUNT: usize = 1000;
+
+/// Reads a link as stored by `set_link`.
+fn parse(value: &str) -> Result<Link, Box<dyn std::error::Error>> {
+    // Entries written before links had metadata are bare URLs.
+    match serde_json::from_str(value) {
+        Ok(link) => Ok(link),
+        Err(_) => Ok(Link {
+            url: value.parse::<Url>()?,
+            created_at: 0,
+            updated_at: 0,
+            meta: Meta::default(),
+        }),
+    }
+}
+
+/// `MGET`, which `Commands::mget` sends as `GET` for a single key.
+fn mget(
+    conn: &mut Connection,
+    keys: &[impl AsRef<str>],
+) -> redis::RedisResult<Vec<Option<String>>> {
+    let mut mget = redis::cmd("MGET");
+    for key in keys {
+        mget.arg(key.as_ref());
+    }
+    mget.query(conn)
+}
+
+/// One `SCAN` from `cursor`, with the links of the keys found. Returns the
+/// cursor to continue from, 0 once all keys were scanned.
+fn scan(
+    conn: &mut Connection,
+    cursor: u64,
+    count: usize,
+) -> redis::RedisResult<(u64, Vec<(Code, Link)>)> {
+    let (next, keys): (u64, Vec<String>) = redis::cmd("SCAN")
+        .arg(cursor)
+        .arg("COUNT")
+        .arg(count)
+        .query(conn)?;
+
+    if keys.is_empty() {
+        return Ok((next, Vec::new()));
+    }
+
+    let values = mget(conn, &keys)?;
+
+    // Keys that aren't links are skipped.
+    let links = keys
+        .into_iter()
+        .zip(values)
+        .filter_map(|(key, value)| Some((Code::new(key), parse(&value?).ok()?)))
+        .collect();
+
+    Ok((next, links))
+}
+
 pub struct Redis {
     pool: Pool<Client>,
     expire: Duration,
@@ -21,57 +85,203 @@ impl Redis {
         Ok(())
     }
 
-    pub fn get(&self, key: &str) -> Result<Url, Box<dyn std::error::Error>> {
+    pub fn get(&self, key: &str) -> Result<Link, Box<dyn std::error::Error>> {
         let mut conn = self.pool.get()?;
 
-        let url: String = conn.get(key)?;
-        let url: Url = url.parse()?;
+        let value: String = conn.get(key)?;
 
-        Ok(url)
+        parse(&value)
+    }
+
+    fn set_link(&self, key: &str, link: &Link) -> Result<(), Box<dyn std::error::Error>> {
+        self.set(key, &serde_json::to_string(link)?)
     }
 }
 
 impl Cache for Redis {
-    fn get(&self, code: &Code) -> Result<Url, error::Load> {
-        self.get(code.as_str()).map_err(|_| error::Load::NotFound)
+    fn get(&self, code: &Code) -> Result<Link, error::Error> {
+        self.get(code.as_str()).map_err(|_| error::Error::NotFound)
     }
 
-    fn set(&self, url: &Url, code: &Code) -> Result<(), error::Storage> {
-        self.set(code.as_str(), url.as_str())
-            .map_err(|_| crate::error::Storage::Duplicate)
+    fn set(&self, link: &Link, code: &Code) -> Result<(), error::Error> {
+        self.set_link(code.as_str(), link)
+            .map_err(|e| error::Error::Unavailable(e.to_string()))
     }
 }
 
 impl Storage for Redis {
-    fn store(&mut self, url: Url, code: &Code) -> Result<(), error::Storage> {
+    fn store(&mut self, link: Link, code: &Code) -> Result<(), error::Error> {
         match self.get(code.as_str()) {
-            Ok(_) => Err(crate::error::Storage::Duplicate),
+            Ok(_) => Err(error::Error::Duplicate),
             Err(_) => {
-                self.set(code.as_str(), url.as_str())
-                    .map_err(|e| error::Storage::Internal(e.to_string()))?;
+                self.set_link(code.as_str(), &link)
+                    .map_err(|e| error::Error::Unavailable(e.to_string()))?;
                 Ok(())
             }
         }
     }
 
-    fn load(&self, code: &Code) -> Result<Url, error::Load> {
-        self.get(code.as_str()).map_err(|_| error::Load::NotFound)
+    fn load(&self, code: &Code) -> Result<Link, error::Error> {
+        self.get(code.as_str()).map_err(|_| error::Error::NotFound)
+    }
+
+    fn replace(&mut self, link: Link, code: &Code) -> Result<(), error::Error> {
+        self.set_link(code.as_str(), &link)
+            .map_err(|e| error::Error::Unavailable(e.to_string()))
+    }
+
+    /// Gets all links with one `MGET`.
+    fn load_many(&self, codes: &[Code]) -> Vec<Result<Link, error::Error>> {
+        if codes.is_empty() {
+            return Vec::new();
+        }
+
+        let keys: Vec<&str> = codes.iter().map(Code::as_str).collect();
+
+        let values = self
+            .pool
+            .get()
+            .map_err(error::Error::from)
+            .and_then(|mut conn| {
+                mget(&mut conn, &keys).map_err(|e| error::Error::Unavailable(e.to_string()))
+            });
+
+        match values {
+            Ok(values) => values
+                .into_iter()
+                .map(|value| {
+                    value
+                        .and_then(|value| parse(&value).ok())
+                        .ok_or(error::Error::NotFound)
+                })
+                .collect(),
+            Err(e) => vec![Err(e); codes.len()],
+        }
+    }
+
+    /// Scans keys with `SCAN`, so pages aren't ordered and the cursor is
+    /// Redis'. Pages hold at least `limit` links, except for the last.
+    fn list(&self, query: &Query) -> Result<Page, error::Error> {
+        let mut cursor: u64 = match &query.cursor {
+            Some(cursor) => cursor
+                .parse()
+                .map_err(|_| error::Error::Malformed("invalid cursor".into()))?,
+            None => 0,
+        };
+
+        let mut conn = self.pool.get()?;
+        let mut links = Vec::new();
+
+        loop {
+            let (next, scanned) = scan(&mut conn, cursor, query.limit())
+                .map_err(|e| error::Error::Unavailable(e.to_string()))?;
+
+            links.extend(
+                scanned
+                    .into_iter()
+                    .filter(|(code, link)| query.matches(code, link)),
+            );
+            cursor = next;
+
+            if cursor == 0 || links.len() >= query.limit() {
+                break;
+            }
+        }
+
+        Ok(Page {
+            links,
+            next: (cursor != 0).then(|| cursor.to_string()),
+        })
+    }
+
+    /// Scans every key, Redis has no index to look links up by.
+    fn find(&self, target: &Target) -> Result<Vec<(Code, Link)>, error::Error> {
+        let mut conn = self.pool.get()?;
+        let mut links = Vec::new();
+        let mut cursor = 0;
+
+        loop {
+            let (next, scanned) = scan(&mut conn, cursor, SCAN_COUNT)
+                .map_err(|e| error::Error::Unavailable(e.to_string()))?;
+
+            links.extend(
+                scanned
+                    .into_iter()
+                    .filter(|(_, link)| target.matches(&link.url)),
+            );
+            cursor = next;
+
+            if cursor == 0 {
+                return Ok(links);
+            }
+        }
+    }
+
+    fn retarget(&mut self, from: &str, to: &str) -> Result<Vec<(Code, Link)>, error::Error> {
+        let mut moved = self.find(&Target::Domain(from.to_string()))?;
+
+        for (code, link) in &mut moved {
+            link.url = lookup::retarget(&link.url, from, to).expect("found links are on `from`");
+            link.updated_at = link::now();
+
+            self.set_link(code.as_str(), link)
+                .map_err(|e| error::Error::Unavailable(e.to_string()))?;
+        }
+
+        Ok(moved)
+    }
+
+    /// Sets all links in one pipeline, only where their codes aren't set.
+    fn store_many(&mut self, links: Vec<(Link, Code)>) -> Vec<Result<(), error::Error>> {
+        let count = links.len();
+
+        let stored = || -> Result<Vec<Result<(), error::Error>>, Box<dyn std::error::Error>> {
+            let mut pipe = redis::pipe();
+
+            for (link, code) in &links {
+                pipe.cmd("SET")
+                    .arg(code.as_str())
+                    .arg(serde_json::to_string(link)?)
+                    .arg("NX")
+                    .arg("EX")
+                    .arg(self.expire.as_secs());
+            }
+
+            let replies: Vec<Option<String>> = pipe.query(&mut *self.pool.get()?)?;
+
+            Ok(replies
+                .into_iter()
+                .map(|reply| reply.map(|_| ()).ok_or(error::Error::Duplicate))
+                .collect())
+        };
+
+        stored().unwrap_or_else(|e| vec![Err(error::Error::Unavailable(e.to_string())); count])
     }
 }
 
-impl Default for Redis {
-    fn default() -> Self {
-        let url = std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1/".to_string());
-        let client = Client::open(url).expect("Invalid Redis URL");
+impl Redis {
+    pub fn connect(url: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let client = Client::open(url)?;
 
         let pool = Pool::builder()
             .connection_timeout(Duration::from_secs(5))
-            .build(client)
-            .expect("Failed to create Redis pool");
+            .build(client)?;
 
-        Self {
+        Ok(Self {
             pool,
             expire: Duration::from_secs(300),
-        }
+        })
+    }
+
+    /// Connects to `REDIS_URL`, or a local server if it's unset.
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
+        let url = std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1/".to_string());
+        Self::connect(&url)
+    }
+}
+
+impl Default for Redis {
+    fn default() -> Self {
+        Self::from_env().expect("Failed to create Redis pool")
     }
 }
@@ -0,0 +1,170 @@
+use std::collections::HashSet;
+
+use url::Url;
+
+/// Comes up with aliases close to one that's invalid or taken.
+pub struct Suggester {
+    separator: char,
+    /// How many suggestions are made at most.
+    pub count: usize,
+}
+
+/// Words put in front of an alias.
+const PREFIXES: &[&str] = &["my", "get", "the"];
+
+/// Words put after an alias.
+const SUFFIXES: &[&str] = &["link", "go", "now"];
+
+/// Numbers appended to an alias, after the other variants.
+const NUMBERS: std::ops::RangeInclusive<usize> = 2..=9;
+
+impl Suggester {
+    pub fn new(separator: char, count: usize) -> Self {
+        Self { separator, count }
+    }
+
+    /// Replaces runs of anything but letters and digits by the separator.
+    fn slugify(&self, s: &str) -> String {
+        s.split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .collect::<Vec<_>>()
+            .join(&self.separator.to_string())
+    }
+
+    /// Words of the destination that make for an alias: the last path
+    /// segment and the host's name.
+    fn destination_words(&self, url: &Url) -> Vec<String> {
+        let segment = url
+            .path_segments()
+            .and_then(|mut segments| segments.rfind(|s| !s.is_empty()))
+            .map(|segment| segment.rsplit_once('.').map_or(segment, |(stem, _)| stem));
+
+        let host = url
+            .host_str()
+            .map(|host| host.trim_start_matches("www."))
+            .and_then(|host| host.split('.').next());
+
+        segment
+            .into_iter()
+            .chain(host)
+            .map(|word| self.slugify(&word.to_lowercase()))
+            .filter(|word| !word.is_empty())
+            .collect()
+    }
+
+    /// Candidates for `alias`, best first, not yet checked for validity or
+    /// availability.
+    pub fn candidates(&self, alias: &str, url: Option<&Url>) -> Vec<String> {
+        let sep = self.separator;
+        let slug = self.slugify(alias);
+
+        // A number the client already tried is counted up from.
+        let (base, start) = match slug.rsplit_once(sep) {
+            Some((base, n)) if !base.is_empty() => match n.parse::<usize>() {
+                Ok(n) => (base.to_string(), n + 1),
+                Err(_) => (slug.clone(), *NUMBERS.start()),
+            },
+            _ => (slug.clone(), *NUMBERS.start()),
+        };
+
+        let mut candidates = Vec::new();
+
+        if base.is_empty() {
+            return candidates;
+        }
+
+        candidates.push(base.clone());
+
+        let words: Vec<&str> = base.split(sep).collect();
+        if words.len() > 1 {
+            candidates.push(words.concat());
+            candidates.push(
+                words
+                    .iter()
+                    .rev()
+                    .copied()
+                    .collect::<Vec<_>>()
+                    .join(&sep.to_string()),
+            );
+        }
+
+        for word in url
+            .map(|url| self.destination_words(url))
+            .unwrap_or_default()
+        {
+            candidates.push(format!("{base}{sep}{word}"));
+            candidates.push(format!("{word}{sep}{base}"));
+        }
+
+        candidates.extend(SUFFIXES.iter().map(|suffix| format!("{base}{sep}{suffix}")));
+        candidates.extend(PREFIXES.iter().map(|prefix| format!("{prefix}{sep}{base}")));
+        candidates.extend((start..start + NUMBERS.count()).map(|n| format!("{base}{sep}{n}")));
+
+        let mut seen = HashSet::new();
+        candidates.retain(|candidate| candidate != alias && seen.insert(candidate.clone()));
+
+        candidates
+    }
+}
+
+impl Default for Suggester {
+    fn default() -> Self {
+        Self::new('-', 5)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidates(alias: &str, url: Option<&str>) -> Vec<String> {
+        let url: Option<Url> = url.map(|url| url.parse().unwrap());
+        Suggester::default().candidates(alias, url.as_ref())
+    }
+
+    #[test]
+    fn numeric_suffixes_come_last() {
+        let candidates = candidates("blaze", None);
+
+        assert_eq!(candidates.first().unwrap(), "blaze-link");
+        assert_eq!(candidates.last().unwrap(), "blaze-9");
+        assert!(candidates.contains(&"my-blaze".to_string()));
+    }
+
+    #[test]
+    fn numbers_are_counted_up() {
+        let candidates = candidates("blaze-4", None);
+
+        assert_eq!(candidates.first().unwrap(), "blaze");
+        assert!(candidates.contains(&"blaze-5".to_string()));
+        assert!(!candidates.contains(&"blaze-4".to_string()));
+    }
+
+    #[test]
+    fn words_are_joined_and_swapped() {
+        let candidates = candidates("brave-otter", None);
+
+        assert!(candidates.contains(&"braveotter".to_string()));
+        assert!(candidates.contains(&"otter-brave".to_string()));
+    }
+
+    #[test]
+    fn destination_adds_variants() {
+        let candidates = candidates(
+            "blaze",
+            Some("https://www.Example.com/docs/Get_Started.html"),
+        );
+
+        assert!(candidates.contains(&"blaze-get-started".to_string()));
+        assert!(candidates.contains(&"example-blaze".to_string()));
+    }
+
+    #[test]
+    fn invalid_characters_become_separators() {
+        assert_eq!(
+            candidates("blaze/fast!", None).first().unwrap(),
+            "blaze-fast"
+        );
+        assert!(candidates("///", None).is_empty());
+    }
+}
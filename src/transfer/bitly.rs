@@ -0,0 +1,84 @@
+use std::io::Read;
+
+use super::{
+    foreign::{self, Fields},
+    Record,
+};
+use crate::{error, link::Meta};
+
+/// Names Bitly's exports have used for each column, lowercase with
+/// underscores as spaces.
+const BITLINK: [&str; 5] = ["bitlink", "short link", "short url", "link", "id"];
+const LONG_URL: [&str; 4] = ["long url", "destination", "destination url", "original url"];
+const CREATED: [&str; 4] = ["created", "created at", "date created", "creation date"];
+
+/// Reads links from a CSV export of Bitly links, keeping the back-half of
+/// each bitlink as its code.
+pub fn read(input: impl Read) -> Result<Vec<Result<Record, String>>, error::Error> {
+    Ok(foreign::csv_fields(input)?
+        .into_iter()
+        .map(|fields| fields.and_then(|fields| record(&fields)))
+        .collect())
+}
+
+fn record(fields: &Fields) -> Result<Record, String> {
+    let field = |names: &[&str]| {
+        names
+            .iter()
+            .find_map(|name| fields.get(*name).filter(|value| !value.is_empty()))
+    };
+
+    let bitlink = field(&BITLINK).ok_or("bitlink is missing")?;
+    let code = back_half(bitlink).ok_or_else(|| format!("bitlink {bitlink} has no back-half"))?;
+
+    foreign::record(
+        code,
+        field(&LONG_URL).ok_or("long url is missing")?,
+        Meta {
+            title: field(&["title"]).cloned(),
+            tags: field(&["tags"]).map_or_else(Vec::new, |tags| foreign::tags(tags)),
+            ..Meta::default()
+        },
+        field(&CREATED).and_then(|created| foreign::timestamp(created)),
+    )
+}
+
+/// The code of a bitlink: `3AbC` of `https://bit.ly/3AbC`.
+fn back_half(bitlink: &str) -> Option<&str> {
+    let path = bitlink
+        .split_once("://")
+        .map_or(bitlink, |(_, rest)| rest)
+        .split(['?', '#'])
+        .next()?
+        .trim_end_matches('/');
+    let (_, code) = path.rsplit_once('/')?;
+
+    (!code.is_empty()).then_some(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn back_halves_become_codes() {
+        assert_eq!(back_half("https://bit.ly/3AbC"), Some("3AbC"));
+        assert_eq!(back_half("bit.ly/blaze/"), Some("blaze"));
+        assert_eq!(back_half("go.example.com/blaze?utm=1"), Some("blaze"));
+        assert_eq!(back_half("bit.ly"), None);
+    }
+
+    #[test]
+    fn columns_are_read_by_any_of_their_names() {
+        let csv = "Title,Bitlink,Long_URL,Created,Tags\n\
+                   Blaze,bit.ly/blaze,https://blazinglyfast.net/,2024-02-29T12:30:00+0000,rust; fast\n";
+        let records = read(csv.as_bytes()).unwrap();
+        let record = records[0].as_ref().unwrap();
+
+        assert_eq!(record.code, "blaze");
+        assert_eq!(record.link.url.as_str(), "https://blazinglyfast.net/");
+        assert_eq!(record.link.meta.title.as_deref(), Some("Blaze"));
+        assert_eq!(record.link.meta.tags, ["rust", "fast"]);
+        assert_eq!(record.link.created_at, 1709209800);
+    }
+}
@@ -0,0 +1,221 @@
+use std::{collections::HashMap, io::Read, str::FromStr};
+
+use serde::Deserialize;
+
+use super::{bitly, kutt, malformed, yourls, Record};
+use crate::{
+    error,
+    link::{self, Link, Meta},
+    validator::{Validate, Validator},
+};
+
+/// The fields of a row by column name.
+pub(super) type Fields = HashMap<String, String>;
+
+/// Other shorteners' exports links can be imported from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Source {
+    /// A MySQL dump of YOURLS' `yourls_url` table.
+    YourlsSql,
+    /// YOURLS links as CSV, with `keyword` and `url` columns.
+    YourlsCsv,
+    /// Bitly's CSV export of links.
+    Bitly,
+    /// Kutt's links, as listed by its API.
+    Kutt,
+}
+
+impl FromStr for Source {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "yourls-sql" => Ok(Source::YourlsSql),
+            "yourls-csv" => Ok(Source::YourlsCsv),
+            "bitly" => Ok(Source::Bitly),
+            "kutt" => Ok(Source::Kutt),
+            _ => Err(format!(
+                "unknown source {s}, expected yourls-sql, yourls-csv, bitly or kutt"
+            )),
+        }
+    }
+}
+
+/// Reads the links of an export from `source`, keeping their codes.
+/// Records with codes `validator` doesn't allow are reported, as are links
+/// using features shrink doesn't have.
+pub fn read<V: Validate>(
+    source: Source,
+    input: impl Read,
+    validator: &Validator<V>,
+) -> Result<Vec<Result<Record, String>>, error::Error> {
+    let records = match source {
+        Source::YourlsSql => yourls::read_sql(input)?,
+        Source::YourlsCsv => yourls::read_csv(input)?,
+        Source::Bitly => bitly::read(input)?,
+        Source::Kutt => kutt::read(input)?,
+    };
+
+    Ok(records
+        .into_iter()
+        .map(|record| record.and_then(|record| allowed(record, validator)))
+        .collect())
+}
+
+/// The record with its code normalized, if the code is allowed.
+fn allowed<V: Validate>(record: Record, validator: &Validator<V>) -> Result<Record, String> {
+    match validator.check(record.code.clone()) {
+        Ok(code) => Ok(Record {
+            code: code.as_str().to_string(),
+            ..record
+        }),
+        Err(failure) => Err(format!(
+            "code {} isn't supported, it {failure}",
+            record.code
+        )),
+    }
+}
+
+/// A record of a link to `url`, created now unless `created_at` is known.
+pub(super) fn record(
+    code: &str,
+    url: &str,
+    meta: Meta,
+    created_at: Option<i64>,
+) -> Result<Record, String> {
+    let url = url
+        .trim()
+        .parse()
+        .map_err(|e| format!("url {url} isn't valid: {e}"))?;
+    let created_at = created_at.unwrap_or_else(link::now);
+
+    Ok(Record {
+        code: code.trim().to_string(),
+        link: Link {
+            url,
+            created_at,
+            updated_at: created_at,
+            meta,
+        },
+    })
+}
+
+/// Reads CSV rows as fields named after the header, in lowercase and with
+/// underscores as spaces, so `Long URL` and `long_url` are the same.
+pub(super) fn csv_fields(input: impl Read) -> Result<Vec<Result<Fields, String>>, error::Error> {
+    let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(input);
+    let header: Vec<String> = reader
+        .headers()
+        .map_err(malformed)?
+        .iter()
+        .map(|name| name.trim().to_lowercase().replace('_', " "))
+        .collect();
+
+    Ok(reader
+        .records()
+        .map(|row| {
+            let row = row.map_err(|e| e.to_string())?;
+            Ok(header
+                .iter()
+                .cloned()
+                .zip(row.iter().map(|value| value.trim().to_string()))
+                .collect())
+        })
+        .collect())
+}
+
+/// Splits a list of tags on commas, semicolons or pipes.
+pub(super) fn tags(s: &str) -> Vec<String> {
+    s.split([',', ';', '|'])
+        .map(str::trim)
+        .filter(|tag| !tag.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Parses `YYYY-MM-DD HH:MM:SS` as a Unix timestamp, with a `T` instead of
+/// the space, fractional seconds and a `Z` or `±HH:MM` offset allowed. Times
+/// without an offset are taken as UTC.
+pub(super) fn timestamp(s: &str) -> Option<i64> {
+    let s = s.trim();
+    let number = |range: std::ops::Range<usize>| -> Option<i64> {
+        let digits = s.get(range)?;
+        digits
+            .bytes()
+            .all(|b| b.is_ascii_digit())
+            .then(|| digits.parse().ok())?
+    };
+
+    if s.len() < 19 || !matches!(s.as_bytes()[10], b' ' | b'T') {
+        return None;
+    }
+
+    let (year, month, day) = (number(0..4)?, number(5..7)?, number(8..10)?);
+    let (hour, minute, second) = (number(11..13)?, number(14..16)?, number(17..19)?);
+
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 {
+        return None;
+    }
+
+    let rest = s[19..].trim_start_matches(|c: char| c == '.' || c.is_ascii_digit());
+    let offset = match rest {
+        "" | "Z" => 0,
+        _ => {
+            let sign = match rest.as_bytes()[0] {
+                b'+' => 1,
+                b'-' => -1,
+                _ => return None,
+            };
+            let digits = rest[1..].replace(':', "");
+            if digits.len() != 4 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            let (hours, minutes): (i64, i64) =
+                (digits[..2].parse().ok()?, digits[2..].parse().ok()?);
+            sign * (hours * 3600 + minutes * 60)
+        }
+    };
+
+    // Days since 1970-01-01, after Howard Hinnant's `days_from_civil`.
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146097 + day_of_era - 719468;
+
+    Some(days * 86400 + hour * 3600 + minute * 60 + second - offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validator::Alnum;
+
+    #[test]
+    fn timestamps_in_common_forms() {
+        assert_eq!(timestamp("1970-01-01 00:00:00"), Some(0));
+        assert_eq!(timestamp("2024-02-29T12:30:00Z"), Some(1709209800));
+        assert_eq!(timestamp("2024-02-29T12:30:00.123Z"), Some(1709209800));
+        assert_eq!(timestamp("2024-02-29T14:30:00+02:00"), Some(1709209800));
+        assert_eq!(timestamp("2024-02-29T07:30:00-0500"), Some(1709209800));
+        assert_eq!(timestamp("2024-13-01 00:00:00"), None);
+        assert_eq!(timestamp("yesterday"), None);
+    }
+
+    #[test]
+    fn codes_must_be_allowed() {
+        let record = |code: &str| Record {
+            code: code.into(),
+            link: crate::link::Link::new("https://blazinglyfast.net/".parse().unwrap()),
+        };
+        let validator = Validator::new(Alnum);
+
+        assert!(allowed(record("blaze"), &validator).is_ok());
+        assert_eq!(
+            allowed(record("bl@ze"), &validator).unwrap_err(),
+            "code bl@ze isn't supported, it must be alphanumeric"
+        );
+    }
+}
@@ -0,0 +1,112 @@
+use std::io::Read;
+
+use serde::Deserialize;
+
+use super::{foreign, malformed, Record};
+use crate::{error, link::Meta};
+
+/// A link as Kutt's API lists it, with the fields that matter here.
+#[derive(Deserialize)]
+struct Link {
+    address: String,
+    target: String,
+    description: Option<String>,
+    created_at: Option<String>,
+    updated_at: Option<String>,
+    /// Whether visitors have to enter a password.
+    #[serde(default)]
+    password: bool,
+    #[serde(default)]
+    banned: bool,
+    expire_in: Option<String>,
+}
+
+/// Reads links from Kutt's `GET /api/v2/links` response, or the array of
+/// links in it. Links using features shrink doesn't have aren't imported.
+pub fn read(input: impl Read) -> Result<Vec<Result<Record, String>>, error::Error> {
+    let value: serde_json::Value = serde_json::from_reader(input).map_err(malformed)?;
+    let links = match value {
+        serde_json::Value::Array(links) => links,
+        serde_json::Value::Object(mut response) => match response.remove("data") {
+            Some(serde_json::Value::Array(links)) => links,
+            _ => return Err(malformed("expected the links in data")),
+        },
+        _ => return Err(malformed("expected links")),
+    };
+
+    Ok(links
+        .into_iter()
+        .map(|link| {
+            serde_json::from_value(link)
+                .map_err(|e| e.to_string())
+                .and_then(record)
+        })
+        .collect())
+}
+
+fn record(link: Link) -> Result<Record, String> {
+    let unsupported = |feature| Err(format!("link {} {feature}", link.address));
+
+    if link.password {
+        return unsupported("is password protected, which isn't supported");
+    }
+    if link.banned {
+        return unsupported("is banned");
+    }
+    if link.expire_in.is_some() {
+        return unsupported("expires, which isn't supported");
+    }
+
+    let created_at = link.created_at.as_deref().and_then(foreign::timestamp);
+    let mut record = foreign::record(
+        &link.address,
+        &link.target,
+        Meta {
+            title: link
+                .description
+                .filter(|description| !description.is_empty()),
+            ..Meta::default()
+        },
+        created_at,
+    )?;
+
+    if let Some(updated_at) = link.updated_at.as_deref().and_then(foreign::timestamp) {
+        record.link.updated_at = updated_at;
+    }
+
+    Ok(record)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn links_are_read_from_the_response() {
+        let response = r#"{"total": 2, "data": [
+            {"address": "blaze", "target": "https://blazinglyfast.net/", "description": "Blaze",
+             "created_at": "2024-02-29T12:30:00.000Z", "updated_at": "2024-03-01T00:00:00.000Z",
+             "password": false, "banned": false, "expire_in": null, "visit_count": 3},
+            {"address": "secret", "target": "https://rustacean.net/", "password": true}
+        ]}"#;
+        let records = read(response.as_bytes()).unwrap();
+
+        let blaze = records[0].as_ref().unwrap();
+        assert_eq!(blaze.code, "blaze");
+        assert_eq!(blaze.link.meta.title.as_deref(), Some("Blaze"));
+        assert_eq!(blaze.link.created_at, 1709209800);
+        assert_eq!(blaze.link.updated_at, 1709251200);
+
+        assert_eq!(
+            records[1],
+            Err("link secret is password protected, which isn't supported".into())
+        );
+    }
+
+    #[test]
+    fn bare_arrays_are_read() {
+        let links = r#"[{"address": "blaze", "target": "https://blazinglyfast.net/"}]"#;
+
+        assert_eq!(read(links.as_bytes()).unwrap().len(), 1);
+    }
+}
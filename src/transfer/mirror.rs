@@ -0,0 +1,393 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    fs, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use super::each;
+use crate::{error, Storage};
+
+/// Status the mirrors redirect with, the same as shrink's.
+const STATUS: u16 = 307;
+
+/// What's known of the pages in an HTML mirror, kept in its directory.
+const MANIFEST: &str = ".shrink-mirror.json";
+
+/// Bumped when pages look different, so that they're all written again.
+const VERSION: u32 = 1;
+
+/// Static configs links can be served from without shrink.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    /// An nginx `map` of paths to `$shrink_redirect`.
+    Nginx,
+    /// An Apache `RewriteMap` text file.
+    Apache,
+    /// A Netlify or Cloudflare Pages `_redirects` file.
+    Redirects,
+    /// A directory with a meta refresh page per link.
+    Html,
+}
+
+impl FromStr for Kind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "nginx" => Ok(Kind::Nginx),
+            "apache" => Ok(Kind::Apache),
+            "redirects" => Ok(Kind::Redirects),
+            "html" => Ok(Kind::Html),
+            _ => Err(format!(
+                "unknown mirror {s}, expected nginx, apache, redirects or html"
+            )),
+        }
+    }
+}
+
+/// What regenerating a mirror did.
+#[derive(Debug, Default, Serialize)]
+pub struct Report {
+    /// Links the mirror redirects.
+    pub links: usize,
+    /// Files written, none if the mirror was up to date.
+    pub written: usize,
+    /// Pages of links that are gone, removed.
+    pub removed: usize,
+    /// Links whose code can't be served by the mirror.
+    pub skipped: Vec<Skipped>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct Skipped {
+    pub code: String,
+    pub reason: String,
+}
+
+/// Destinations of the pages in an HTML mirror, by code.
+#[derive(Default, Serialize, Deserialize)]
+struct Manifest {
+    version: u32,
+    links: BTreeMap<String, String>,
+}
+
+/// Brings the mirror at `path` up to date with every stored link. Files
+/// that wouldn't change aren't written, and pages only for links that are
+/// new or have moved.
+pub fn mirror<S: Storage + ?Sized>(
+    storage: &S,
+    kind: Kind,
+    path: &Path,
+) -> Result<Report, error::Error> {
+    let mut links = BTreeMap::new();
+    each(storage, |code, link| {
+        links.insert(code.as_str().to_string(), link.url);
+        Ok(())
+    })?;
+
+    let mut report = Report::default();
+    links.retain(|code, _| match unsupported(kind, code) {
+        Some(reason) => {
+            report.skipped.push(Skipped {
+                code: code.clone(),
+                reason: reason.into(),
+            });
+            false
+        }
+        None => true,
+    });
+    report.links = links.len();
+
+    match kind {
+        Kind::Html => pages(&links, path, &mut report)?,
+        _ => report.written = replace(path, render(kind, &links).as_bytes())? as usize,
+    }
+
+    Ok(report)
+}
+
+/// Why a code can't be served by the mirror, if it can't.
+fn unsupported(kind: Kind, code: &str) -> Option<&'static str> {
+    if code.chars().any(char::is_control) {
+        return Some("has control characters");
+    }
+
+    match kind {
+        Kind::Apache if code.contains(char::is_whitespace) => {
+            Some("has whitespace, which RewriteMap keys can't")
+        }
+        Kind::Apache if code.starts_with('#') => {
+            Some("starts with #, which RewriteMap takes for a comment")
+        }
+        Kind::Html
+            if code
+                .split('/')
+                .any(|segment| segment.is_empty() || segment.starts_with('.'))
+                || code.contains('\\') =>
+        {
+            Some("isn't a safe path for a page")
+        }
+        _ => None,
+    }
+}
+
+/// The single file mirror of `links`.
+fn render(kind: Kind, links: &BTreeMap<String, Url>) -> String {
+    let mut out = String::new();
+
+    match kind {
+        Kind::Nginx => {
+            let quote = |s: &str| format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""));
+
+            let _ = writeln!(
+                out,
+                "# Links exported by shrink. Include this in the http block, and in a server:\n\
+                 #   if ($shrink_redirect) {{ return {STATUS} $shrink_redirect; }}\n\
+                 # Many links may need a larger map_hash_max_size.\n\
+                 map $uri $shrink_redirect {{"
+            );
+            for (code, url) in links {
+                // Values can hold variables, `$` mustn't start one.
+                let url = url.as_str().replace('$', "%24");
+                let _ = writeln!(out, "    {} {};", quote(&format!("/{code}")), quote(&url));
+            }
+            out.push_str("}\n");
+        }
+        Kind::Apache => {
+            let _ = writeln!(
+                out,
+                "# Links exported by shrink. In a server config:\n\
+                 #   RewriteEngine on\n\
+                 #   RewriteMap shrink \"txt:/path/to/this/file\"\n\
+                 #   RewriteCond ${{shrink:$1}} .\n\
+                 #   RewriteRule ^/(.+)$ ${{shrink:$1}} [R={STATUS},NE,L]"
+            );
+            for (code, url) in links {
+                let _ = writeln!(out, "{code} {url}");
+            }
+        }
+        Kind::Redirects => {
+            out.push_str("# Links exported by shrink.\n");
+            for (code, url) in links {
+                let _ = writeln!(out, "{} {url} {STATUS}", path(code));
+            }
+        }
+        Kind::Html => unreachable!("HTML mirrors are directories"),
+    }
+
+    out
+}
+
+/// `code` as a percent-encoded path, without the placeholders and splats
+/// of `_redirects`.
+fn path(code: &str) -> String {
+    let mut url = Url::parse("http://localhost/").expect("valid base");
+    url.set_path(code);
+
+    url.path().replace('*', "%2A").replace(':', "%3A")
+}
+
+/// A page sending visitors on to `url`.
+fn page(url: &Url) -> String {
+    let url = url
+        .as_str()
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;");
+
+    format!(
+        "<!DOCTYPE html>\n\
+         <html>\n\
+         <head>\n\
+         <meta charset=\"utf-8\">\n\
+         <meta name=\"robots\" content=\"noindex\">\n\
+         <meta http-equiv=\"refresh\" content=\"0; url={url}\">\n\
+         <link rel=\"canonical\" href=\"{url}\">\n\
+         <title>Redirecting…</title>\n\
+         </head>\n\
+         <body>\n\
+         <p>Redirecting to <a href=\"{url}\">{url}</a>.</p>\n\
+         </body>\n\
+         </html>\n"
+    )
+}
+
+/// Writes `code/index.html` in `dir` for links that are new or have moved
+/// since the manifest was written, and removes the pages of links that are
+/// gone.
+fn pages(
+    links: &BTreeMap<String, Url>,
+    dir: &Path,
+    report: &mut Report,
+) -> Result<(), error::Error> {
+    fs::create_dir_all(dir).map_err(failed(dir))?;
+
+    let manifest_path = dir.join(MANIFEST);
+    let manifest = match fs::read(&manifest_path) {
+        // A manifest that can't be read only costs writing every page.
+        Ok(manifest) => serde_json::from_slice(&manifest).unwrap_or_default(),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Manifest::default(),
+        Err(e) => return Err(failed(&manifest_path)(e)),
+    };
+    let current = manifest.version == VERSION;
+
+    for (code, url) in links {
+        let written = manifest.links.get(code).map(String::as_str);
+        if current && written == Some(url.as_str()) {
+            continue;
+        }
+
+        let page_dir = dir.join(code);
+        fs::create_dir_all(&page_dir).map_err(failed(&page_dir))?;
+        report.written += replace(&page_dir.join("index.html"), page(url).as_bytes())? as usize;
+    }
+
+    let gone = manifest
+        .links
+        .keys()
+        .filter(|code| !links.contains_key(*code))
+        .filter(|code| unsupported(Kind::Html, code).is_none());
+    for code in gone {
+        remove(dir, code)?;
+        report.removed += 1;
+    }
+
+    let manifest = Manifest {
+        version: VERSION,
+        links: links
+            .iter()
+            .map(|(code, url)| (code.clone(), url.to_string()))
+            .collect(),
+    };
+    let manifest =
+        serde_json::to_vec(&manifest).map_err(|e| error::Error::Internal(e.to_string()))?;
+    replace(&manifest_path, &manifest)?;
+
+    Ok(())
+}
+
+/// Removes the page of `code` in `dir`, and the directories left empty.
+fn remove(dir: &Path, code: &str) -> Result<(), error::Error> {
+    let page = dir.join(code).join("index.html");
+
+    match fs::remove_file(&page) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(failed(&page)(e)),
+        _ => (),
+    }
+
+    for parent in Path::new(code)
+        .ancestors()
+        .filter(|parent| parent != &Path::new(""))
+    {
+        if fs::remove_dir(dir.join(parent)).is_err() {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Replaces the file at `path` with `contents` unless it holds them already,
+/// so that it's never seen half written. Returns whether it was written.
+fn replace(path: &Path, contents: &[u8]) -> Result<bool, error::Error> {
+    if fs::read(path).is_ok_and(|old| old == contents) {
+        return Ok(false);
+    }
+
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temporary: PathBuf = path.with_file_name(format!(".{name}.tmp"));
+
+    fs::write(&temporary, contents).map_err(failed(&temporary))?;
+    fs::rename(&temporary, path).map_err(failed(path))?;
+
+    Ok(true)
+}
+
+fn failed(path: &Path) -> impl Fn(io::Error) -> error::Error + '_ {
+    move |e| error::Error::Internal(format!("{}: {e}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{link::Link, storage::Memory, validator::Code};
+
+    fn links(links: &[(&str, &str)]) -> BTreeMap<String, Url> {
+        links
+            .iter()
+            .map(|(code, url)| (code.to_string(), url.parse().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn configs_escape_what_they_must() {
+        let links = links(&[("blaze", "https://example.com/$1?q=\"a\"")]);
+
+        assert!(render(Kind::Nginx, &links)
+            .contains("    \"/blaze\" \"https://example.com/%241?q=%22a%22\";\n"));
+        assert!(
+            render(Kind::Apache, &links).ends_with("\nblaze https://example.com/$1?q=%22a%22\n")
+        );
+        assert_eq!(path("bläze *:x"), "/bl%C3%A4ze%20%2A%3Ax");
+        assert!(page(&"https://example.com/?a=1&b=<2>".parse().unwrap())
+            .contains("url=https://example.com/?a=1&amp;b=%3C2%3E\""));
+    }
+
+    #[test]
+    fn codes_that_cant_be_served_are_skipped() {
+        assert_eq!(unsupported(Kind::Nginx, "two words"), None);
+        assert!(unsupported(Kind::Apache, "two words").is_some());
+        assert!(unsupported(Kind::Html, "../etc").is_some());
+        assert!(unsupported(Kind::Html, "a//b").is_some());
+        assert_eq!(unsupported(Kind::Html, "docs/blaze"), None);
+    }
+
+    #[test]
+    fn html_mirrors_are_regenerated_incrementally() {
+        let dir = std::env::temp_dir().join(format!("shrink-mirror-{}", std::process::id()));
+        let mut urls = Memory::default();
+        let link = |url: &str| Link::new(url.parse().unwrap());
+
+        urls.store(
+            link("https://blazinglyfast.net/"),
+            &Code::new("blaze".into()),
+        )
+        .unwrap();
+        urls.store(
+            link("https://rustacean.net/"),
+            &Code::new("crab/ferris".into()),
+        )
+        .unwrap();
+
+        let report = mirror(&urls, Kind::Html, &dir).unwrap();
+        assert_eq!((report.links, report.written, report.removed), (2, 2, 0));
+        assert!(dir.join("crab/ferris/index.html").exists());
+
+        let report = mirror(&urls, Kind::Html, &dir).unwrap();
+        assert_eq!((report.written, report.removed), (0, 0));
+
+        let mut urls = Memory::default();
+        urls.store(
+            link("https://www.rust-lang.org/"),
+            &Code::new("blaze".into()),
+        )
+        .unwrap();
+
+        let report = mirror(&urls, Kind::Html, &dir).unwrap();
+        assert_eq!((report.written, report.removed), (1, 1));
+        assert!(!dir.join("crab").exists());
+        assert!(fs::read_to_string(dir.join("blaze/index.html"))
+            .unwrap()
+            .contains("https://www.rust-lang.org/"));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,768 @@
+mod bitly;
+pub mod foreign;
+mod kutt;
+pub mod mirror;
+mod yourls;
+
+use std::{
+    collections::HashSet,
+    io::{BufRead, BufReader, Read, Write},
+    str::FromStr,
+};
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::{
+    app::Checks,
+    error,
+    link::{Link, Meta},
+    listing::{Query, MAX_LIMIT},
+    validator::{Code, Validate, Validator},
+    Storage,
+};
+
+/// Formats links are imported and exported in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    Csv,
+    /// One array of records.
+    Json,
+    /// One record per line.
+    #[serde(alias = "jsonl")]
+    Ndjson,
+}
+
+/// What's done with imported links whose code is already used.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OnConflict {
+    /// Keep the stored link.
+    #[default]
+    Skip,
+    /// Replace the stored link.
+    Overwrite,
+    /// Import the link under `{code}-2`, `{code}-3`, … instead.
+    Rename,
+    /// Import nothing.
+    Fail,
+}
+
+/// A link with its code, as it's imported and exported.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Record {
+    pub code: String,
+    #[serde(flatten)]
+    pub link: Link,
+}
+
+/// A record as a CSV row, which can't nest. Tags are separated by `;`.
+#[derive(Serialize, Deserialize)]
+struct Row {
+    code: String,
+    url: Url,
+    #[serde(default, deserialize_with = "csv::invalid_option")]
+    created_at: Option<i64>,
+    #[serde(default, deserialize_with = "csv::invalid_option")]
+    updated_at: Option<i64>,
+    #[serde(default)]
+    owner: Option<String>,
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    tags: String,
+    #[serde(default)]
+    notes: Option<String>,
+}
+
+/// What an import did, or would do on a dry run.
+#[derive(Debug, Default, Serialize)]
+pub struct Report {
+    pub dry_run: bool,
+    /// Links stored under their own code.
+    pub imported: usize,
+    /// Links whose code was already used, stored or earlier in the input.
+    pub conflicts: Vec<Conflict>,
+    /// Records that couldn't be read or stored.
+    pub errors: Vec<RecordError>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct Conflict {
+    pub code: String,
+    #[serde(flatten)]
+    pub resolution: Resolution,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "resolution", rename_all = "lowercase")]
+pub enum Resolution {
+    Skipped,
+    Overwritten,
+    Renamed {
+        to: String,
+    },
+    /// Nothing was imported because of it.
+    Failed,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RecordError {
+    /// Position of the record in the input.
+    pub index: usize,
+    pub error: String,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(Format::Csv),
+            "json" => Ok(Format::Json),
+            "ndjson" | "jsonl" => Ok(Format::Ndjson),
+            _ => Err(format!("unknown format {s}, expected csv, json or ndjson")),
+        }
+    }
+}
+
+impl Format {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Format::Csv => "text/csv",
+            Format::Json => "application/json",
+            Format::Ndjson => "application/x-ndjson",
+        }
+    }
+
+    /// The format of a media type, ignoring parameters.
+    pub fn of_content_type(content_type: &str) -> Option<Self> {
+        match content_type.split(';').next()?.trim() {
+            "text/csv" => Some(Format::Csv),
+            "application/json" => Some(Format::Json),
+            "application/x-ndjson" | "application/jsonl" => Some(Format::Ndjson),
+            _ => None,
+        }
+    }
+
+    /// The format of a file, by its extension.
+    pub fn of_path(path: &str) -> Option<Self> {
+        path.rsplit_once('.')?.1.parse().ok()
+    }
+}
+
+impl FromStr for OnConflict {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "skip" => Ok(OnConflict::Skip),
+            "overwrite" => Ok(OnConflict::Overwrite),
+            "rename" => Ok(OnConflict::Rename),
+            "fail" => Ok(OnConflict::Fail),
+            _ => Err(format!(
+                "unknown conflict policy {s}, expected skip, overwrite, rename or fail"
+            )),
+        }
+    }
+}
+
+impl From<Record> for Row {
+    fn from(Record { code, link }: Record) -> Self {
+        Row {
+            code,
+            url: link.url,
+            created_at: Some(link.created_at),
+            updated_at: Some(link.updated_at),
+            owner: link.meta.owner,
+            title: link.meta.title,
+            tags: link.meta.tags.join(";"),
+            notes: link.meta.notes,
+        }
+    }
+}
+
+impl From<Row> for Record {
+    fn from(row: Row) -> Self {
+        Record {
+            code: row.code,
+            link: Link {
+                url: row.url,
+                created_at: row.created_at.unwrap_or_default(),
+                updated_at: row.updated_at.unwrap_or_default(),
+                meta: Meta {
+                    owner: row.owner,
+                    title: row.title,
+                    tags: row
+                        .tags
+                        .split(';')
+                        .map(str::trim)
+                        .filter(|tag| !tag.is_empty())
+                        .map(String::from)
+                        .collect(),
+                    notes: row.notes,
+                },
+            },
+        }
+    }
+}
+
+impl Report {
+    /// Whether every record was imported one way or another, or skipped.
+    pub fn ok(&self) -> bool {
+        self.errors.is_empty()
+            && !self
+                .conflicts
+                .iter()
+                .any(|conflict| conflict.resolution == Resolution::Failed)
+    }
+}
+
+fn malformed(e: impl std::fmt::Display) -> error::Error {
+    error::Error::Malformed(e.to_string())
+}
+
+/// Reads records from `input`, each of which may fail on its own. Fails as
+/// a whole if `input` can't be read at all.
+pub fn read(format: Format, input: impl Read) -> Result<Vec<Result<Record, String>>, error::Error> {
+    let checked = |record: Record| match record.code.is_empty() {
+        true => Err("code is empty".to_string()),
+        false => Ok(record),
+    };
+
+    match format {
+        Format::Csv => Ok(csv::Reader::from_reader(input)
+            .deserialize::<Row>()
+            .map(|row| row.map(Record::from).map_err(|e| e.to_string()))
+            .map(|record| record.and_then(checked))
+            .collect()),
+        Format::Json => {
+            let values: Vec<serde_json::Value> =
+                serde_json::from_reader(input).map_err(malformed)?;
+
+            Ok(values
+                .into_iter()
+                .map(|value| serde_json::from_value(value).map_err(|e| e.to_string()))
+                .map(|record| record.and_then(checked))
+                .collect())
+        }
+        Format::Ndjson => {
+            let mut records = Vec::new();
+
+            for line in BufReader::new(input).lines() {
+                let line = line.map_err(malformed)?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                records.push(
+                    serde_json::from_str(&line)
+                        .map_err(|e| e.to_string())
+                        .and_then(checked),
+                );
+            }
+
+            Ok(records)
+        }
+    }
+}
+
+/// Writes records one at a time in a format.
+pub struct Writer<W: Write> {
+    sink: Sink<W>,
+    written: usize,
+}
+
+enum Sink<W: Write> {
+    Csv(Box<csv::Writer<W>>),
+    Json(W),
+    Ndjson(W),
+}
+
+impl<W: Write> Writer<W> {
+    pub fn new(format: Format, out: W) -> Self {
+        let sink = match format {
+            Format::Csv => Sink::Csv(Box::new(csv::Writer::from_writer(out))),
+            Format::Json => Sink::Json(out),
+            Format::Ndjson => Sink::Ndjson(out),
+        };
+
+        Self { sink, written: 0 }
+    }
+
+    pub fn write(&mut self, record: Record) -> Result<(), error::Error> {
+        let internal = |e: &dyn std::fmt::Display| error::Error::Internal(e.to_string());
+
+        match &mut self.sink {
+            Sink::Csv(out) => out.serialize(Row::from(record)).map_err(|e| internal(&e))?,
+            Sink::Json(out) => {
+                let separator: &[u8] = match self.written {
+                    0 => b"[\n",
+                    _ => b",\n",
+                };
+                out.write_all(separator).map_err(|e| internal(&e))?;
+                serde_json::to_writer(&mut *out, &record).map_err(|e| internal(&e))?;
+            }
+            Sink::Ndjson(out) => {
+                serde_json::to_writer(&mut *out, &record).map_err(|e| internal(&e))?;
+                out.write_all(b"\n").map_err(|e| internal(&e))?;
+            }
+        }
+
+        self.written += 1;
+
+        Ok(())
+    }
+
+    /// Ends the output, returning how many records were written.
+    pub fn finish(self) -> Result<usize, error::Error> {
+        let internal = |e: std::io::Error| error::Error::Internal(e.to_string());
+
+        match self.sink {
+            Sink::Csv(mut out) => out.flush().map_err(internal)?,
+            Sink::Json(mut out) => {
+                let end: &[u8] = match self.written {
+                    0 => b"[]\n",
+                    _ => b"\n]\n",
+                };
+                out.write_all(end)
+                    .and_then(|_| out.flush())
+                    .map_err(internal)?;
+            }
+            Sink::Ndjson(mut out) => out.flush().map_err(internal)?,
+        }
+
+        Ok(self.written)
+    }
+}
+
+/// Writes every stored link to `out`, a page at a time. Returns how many
+/// were exported.
+pub fn export<S: Storage + ?Sized>(
+    storage: &S,
+    format: Format,
+    out: impl Write,
+) -> Result<usize, error::Error> {
+    let mut writer = Writer::new(format, out);
+
+    each(storage, |code, link| {
+        writer.write(Record {
+            code: code.as_str().to_string(),
+            link,
+        })
+    })?;
+
+    writer.finish()
+}
+
+/// Calls `f` with every stored link, listing them a page at a time.
+pub fn each<S: Storage + ?Sized>(
+    storage: &S,
+    mut f: impl FnMut(Code, Link) -> Result<(), error::Error>,
+) -> Result<(), error::Error> {
+    let mut query = Query {
+        limit: Some(MAX_LIMIT),
+        ..Query::default()
+    };
+
+    loop {
+        let page = storage.list(&query)?;
+
+        for (code, link) in page.links {
+            f(code, link)?;
+        }
+
+        match page.next {
+            Some(next) => query.cursor = Some(next),
+            None => return Ok(()),
+        }
+    }
+}
+
+/// The first of `{code}-2`, `{code}-3`, … that's neither stored nor taken
+/// by the import, if `aliases` allows it.
+fn rename<S: Storage + ?Sized, V: Validate>(
+    storage: &S,
+    code: &Code,
+    aliases: &Validator<V>,
+    taken: &mut HashSet<String>,
+) -> Result<Code, String> {
+    let mut n = 2;
+
+    loop {
+        let candidate = format!("{}-{n}", code.as_str());
+        n += 1;
+
+        // Later candidates only get longer, they won't do any better.
+        let candidate = aliases.check(candidate.clone()).map_err(|failure| {
+            format!("code {candidate} isn't allowed as a rename, it {failure}")
+        })?;
+
+        if taken.contains(candidate.as_str()) {
+            continue;
+        }
+
+        match storage.load(&candidate) {
+            Ok(_) => continue,
+            Err(error::Error::NotFound) => {
+                taken.insert(candidate.as_str().to_string());
+                return Ok(candidate);
+            }
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+}
+
+/// `records` with the codes `aliases` allows, normalized, and destinations
+/// that pass `checks`, in their canonical form. Others fail on their own.
+pub fn checked<S: Storage + ?Sized, V: Validate>(
+    records: Vec<Result<Record, String>>,
+    storage: &S,
+    aliases: &Validator<V>,
+    checks: &Checks,
+) -> Vec<Result<Record, String>> {
+    let check = |Record { code, link }: Record| {
+        let code = aliases
+            .check(code.clone())
+            .map_err(|failure| format!("code {code} isn't allowed, it {failure}"))?;
+        let url = checks
+            .vet(link.url, |code| storage.load(code).map(|link| link.url))
+            .map_err(|e| e.to_string())?;
+
+        Ok(Record {
+            code: code.as_str().to_string(),
+            link: Link { url, ..link },
+        })
+    };
+
+    records
+        .into_iter()
+        .map(|record| record.and_then(check))
+        .collect()
+}
+
+/// Imports `records` keeping their codes, resolving conflicts by
+/// `on_conflict`, with renamed codes held to `aliases`. A dry run reports
+/// what would be done without storing anything.
+pub fn import<S: Storage + ?Sized, V: Validate>(
+    storage: &mut S,
+    records: Vec<Result<Record, String>>,
+    aliases: &Validator<V>,
+    on_conflict: OnConflict,
+    dry_run: bool,
+) -> Report {
+    let mut report = Report {
+        dry_run,
+        ..Report::default()
+    };
+
+    let mut valid = Vec::new();
+    for (index, record) in records.into_iter().enumerate() {
+        match record {
+            Ok(Record { code, link }) => valid.push((index, Code::new(code), link)),
+            Err(error) => report.errors.push(RecordError { index, error }),
+        }
+    }
+
+    let codes: Vec<Code> = valid.iter().map(|(_, code, _)| code.clone()).collect();
+    let stored = storage.load_many(&codes);
+
+    // Codes stored or imported so far, renamed codes have to avoid them.
+    let mut taken = HashSet::new();
+    let mut fresh = Vec::new();
+    let mut conflicting = Vec::new();
+
+    for ((index, code, link), stored) in valid.into_iter().zip(stored) {
+        let used = match stored {
+            Ok(_) => true,
+            Err(error::Error::NotFound) => false,
+            Err(e) => {
+                report.errors.push(RecordError {
+                    index,
+                    error: e.to_string(),
+                });
+                continue;
+            }
+        };
+
+        match taken.insert(code.as_str().to_string()) && !used {
+            true => fresh.push((index, code, link)),
+            false => conflicting.push((index, code, link)),
+        }
+    }
+
+    if on_conflict == OnConflict::Fail && !conflicting.is_empty() {
+        report.conflicts = conflicting
+            .into_iter()
+            .map(|(_, code, _)| Conflict {
+                code: code.as_str().to_string(),
+                resolution: Resolution::Failed,
+            })
+            .collect();
+        report.errors.sort_by_key(|error| error.index);
+
+        return report;
+    }
+
+    let imported = fresh.len();
+    let mut replaced = Vec::new();
+
+    for (index, code, link) in conflicting {
+        let resolution = match on_conflict {
+            OnConflict::Skip | OnConflict::Fail => Resolution::Skipped,
+            OnConflict::Overwrite => {
+                replaced.push((index, code.clone(), link));
+                Resolution::Overwritten
+            }
+            OnConflict::Rename => match rename(storage, &code, aliases, &mut taken) {
+                Ok(to) => {
+                    let resolution = Resolution::Renamed {
+                        to: to.as_str().to_string(),
+                    };
+                    fresh.push((index, to, link));
+                    resolution
+                }
+                Err(error) => {
+                    report.errors.push(RecordError { index, error });
+                    continue;
+                }
+            },
+        };
+
+        report.conflicts.push(Conflict {
+            code: code.as_str().to_string(),
+            resolution,
+        });
+    }
+
+    report.imported = imported;
+
+    if !dry_run {
+        let indices: Vec<usize> = fresh.iter().map(|(index, _, _)| *index).collect();
+        let batch = fresh
+            .into_iter()
+            .map(|(_, code, link)| (link, code))
+            .collect();
+
+        for (position, (index, result)) in indices
+            .into_iter()
+            .zip(storage.store_many(batch))
+            .enumerate()
+        {
+            if let Err(e) = result {
+                if position < imported {
+                    report.imported -= 1;
+                }
+                report.errors.push(RecordError {
+                    index,
+                    error: e.to_string(),
+                });
+            }
+        }
+
+        for (index, code, link) in replaced {
+            if let Err(e) = storage.replace(link, &code) {
+                report.errors.push(RecordError {
+                    index,
+                    error: e.to_string(),
+                });
+            }
+        }
+    }
+
+    report.errors.sort_by_key(|error| error.index);
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        canonical::Canonicalizer,
+        chain::Chains,
+        filter::DomainFilter,
+        policy::Policy,
+        storage::Memory,
+        validator::{Reserved, Unicode},
+    };
+
+    fn record(code: &str, url: &str) -> Result<Record, String> {
+        Ok(Record {
+            code: code.into(),
+            link: Link::new(url.parse().unwrap()),
+        })
+    }
+
+    fn stored() -> Memory {
+        let mut storage = Memory::default();
+        storage
+            .store(
+                Link::new("https://old.example/".parse().unwrap()),
+                &Code::new("blaze".into()),
+            )
+            .unwrap();
+        storage
+    }
+
+    fn aliases() -> Validator<Unicode> {
+        Validator::new(Unicode::default())
+    }
+
+    fn url(storage: &Memory, code: &str) -> Option<String> {
+        storage
+            .load(&Code::new(code.into()))
+            .ok()
+            .map(|link| link.url.to_string())
+    }
+
+    #[test]
+    fn conflicts_are_resolved_by_policy() {
+        let records = || {
+            vec![
+                record("blaze", "https://new.example/"),
+                record("fast", "https://fast.example/"),
+            ]
+        };
+
+        let mut storage = stored();
+        let report = import(&mut storage, records(), &aliases(), OnConflict::Skip, false);
+        assert_eq!(report.imported, 1);
+        assert_eq!(url(&storage, "blaze").unwrap(), "https://old.example/");
+
+        let mut storage = stored();
+        import(
+            &mut storage,
+            records(),
+            &aliases(),
+            OnConflict::Overwrite,
+            false,
+        );
+        assert_eq!(url(&storage, "blaze").unwrap(), "https://new.example/");
+
+        let mut storage = stored();
+        let report = import(
+            &mut storage,
+            records(),
+            &aliases(),
+            OnConflict::Rename,
+            false,
+        );
+        assert_eq!(
+            report.conflicts[0].resolution,
+            Resolution::Renamed {
+                to: "blaze-2".into()
+            }
+        );
+        assert_eq!(url(&storage, "blaze-2").unwrap(), "https://new.example/");
+
+        let mut storage = stored();
+        let report = import(&mut storage, records(), &aliases(), OnConflict::Fail, false);
+        assert!(!report.ok());
+        assert_eq!(url(&storage, "fast"), None);
+    }
+
+    #[test]
+    fn renamed_codes_must_be_allowed() {
+        let mut storage = stored();
+        let report = import(
+            &mut storage,
+            vec![record("blaze", "https://new.example/")],
+            &Validator::new(Unicode::new(3, 5, '-')),
+            OnConflict::Rename,
+            false,
+        );
+
+        assert_eq!(
+            report.errors[0].error,
+            "code blaze-2 isn't allowed as a rename, it must be 3 to 5 characters long"
+        );
+        assert_eq!(url(&storage, "blaze-2"), None);
+    }
+
+    #[test]
+    fn dry_runs_store_nothing() {
+        let mut storage = stored();
+        let report = import(
+            &mut storage,
+            vec![
+                record("fast", "https://fast.example/"),
+                record("fast", "https://faster.example/"),
+                Err("missing field `url`".into()),
+            ],
+            &aliases(),
+            OnConflict::Rename,
+            true,
+        );
+
+        assert_eq!(report.imported, 1);
+        assert_eq!(report.conflicts.len(), 1);
+        assert_eq!(report.errors[0].index, 2);
+        assert_eq!(url(&storage, "fast"), None);
+    }
+
+    #[test]
+    fn csv_round_trips() {
+        let mut link = Link::new("https://blazinglyfast.net/".parse().unwrap());
+        link.meta.tags = vec!["rust".into(), "web".into()];
+        link.meta.title = Some("Blazingly, \"Fast\"".into());
+        let record = Record {
+            code: "blaze".into(),
+            link,
+        };
+
+        let mut out = Vec::new();
+        let mut writer = Writer::new(Format::Csv, &mut out);
+        writer.write(record.clone()).unwrap();
+        writer.finish().unwrap();
+
+        let read = read(Format::Csv, out.as_slice()).unwrap();
+        assert_eq!(read, vec![Ok(record)]);
+    }
+
+    #[test]
+    fn csv_needs_only_code_and_url() {
+        let read = read(
+            Format::Csv,
+            "code,url\nblaze,https://blazinglyfast.net/\n".as_bytes(),
+        )
+        .unwrap();
+
+        assert_eq!(read[0].as_ref().unwrap().link.created_at, 0);
+    }
+
+    #[test]
+    fn imports_are_checked_like_new_links() {
+        let (canonical, chains, policy, domains) = (
+            Canonicalizer::default(),
+            Chains::new(vec![], vec![]),
+            Policy::default(),
+            DomainFilter::default(),
+        );
+        let checks = Checks {
+            canonical: &canonical,
+            chains: &chains,
+            policy: &policy,
+            domains: &domains,
+        };
+        let aliases = Validator::new(Reserved::default());
+
+        let checked = checked(
+            vec![
+                record("blaze", "https://blazinglyfast.net/"),
+                record("xss", "javascript:alert(1)"),
+                record("admin", "https://blazinglyfast.net/"),
+            ],
+            &Memory::default(),
+            &aliases,
+            &checks,
+        );
+
+        assert!(checked[0].is_ok());
+        assert!(checked[1].is_err());
+        assert_eq!(
+            checked[2],
+            Err("code admin isn't allowed, it must be not a reserved word".into())
+        );
+    }
+}
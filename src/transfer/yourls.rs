@@ -0,0 +1,275 @@
+use std::io::Read;
+
+use super::{
+    foreign::{self, Fields},
+    malformed, Record,
+};
+use crate::{error, link::Meta};
+
+/// Columns of `yourls_url` in the order YOURLS creates them, for inserts
+/// without a column list.
+const COLUMNS: [&str; 6] = ["keyword", "url", "title", "timestamp", "ip", "clicks"];
+
+/// Reads links from the `INSERT`s into the `yourls_url` table of a SQL
+/// dump, whatever its prefix. Other tables are skipped.
+pub fn read_sql(mut input: impl Read) -> Result<Vec<Result<Record, String>>, error::Error> {
+    let mut dump = String::new();
+    input.read_to_string(&mut dump).map_err(malformed)?;
+
+    // Keywords are matched ignoring case, at the same offsets.
+    let upper = dump.to_ascii_uppercase();
+    let mut records = Vec::new();
+    let mut at = 0;
+
+    while let Some(found) = upper[at..].find("INSERT") {
+        let mut parser = Parser(&dump[at + found + "INSERT".len()..]);
+
+        let Some(table) = parser.header() else {
+            // Not an insert after all, e.g. a word in a comment.
+            at += found + "INSERT".len();
+            continue;
+        };
+        let columns = match parser.eat('(') {
+            true => parser
+                .columns()
+                .ok_or_else(|| malformed("bad column list"))?,
+            false => COLUMNS.iter().map(|column| column.to_string()).collect(),
+        };
+        if !parser.keyword("VALUES") {
+            return Err(malformed(format!(
+                "expected VALUES after INSERT INTO {table}"
+            )));
+        }
+
+        loop {
+            let values = parser
+                .tuple()
+                .ok_or_else(|| malformed(format!("bad row in INSERT INTO {table}")))?;
+
+            if table == "url" || table.ends_with("_url") {
+                let fields: Fields = columns.iter().cloned().zip(values).collect();
+                records.push(record(&fields));
+            }
+            if !parser.eat(',') {
+                break;
+            }
+        }
+        parser.eat(';');
+
+        at = dump.len() - parser.0.len();
+    }
+
+    Ok(records)
+}
+
+/// Reads links from CSV with YOURLS' column names, `keyword` and `url`
+/// being required.
+pub fn read_csv(input: impl Read) -> Result<Vec<Result<Record, String>>, error::Error> {
+    Ok(foreign::csv_fields(input)?
+        .into_iter()
+        .map(|fields| fields.and_then(|fields| record(&fields)))
+        .collect())
+}
+
+fn record(fields: &Fields) -> Result<Record, String> {
+    let field = |name| fields.get(name).filter(|value| !value.is_empty());
+
+    foreign::record(
+        field("keyword").ok_or("keyword is missing")?,
+        field("url").ok_or("url is missing")?,
+        Meta {
+            title: field("title").cloned(),
+            ..Meta::default()
+        },
+        field("timestamp").and_then(|timestamp| foreign::timestamp(timestamp)),
+    )
+}
+
+/// Reads the parts of `INSERT` statements from the start of a string.
+struct Parser<'a>(&'a str);
+
+impl Parser<'_> {
+    fn skip_whitespace(&mut self) {
+        self.0 = self.0.trim_start();
+    }
+
+    /// Consumes `c` if it's next.
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        match self.0.strip_prefix(c) {
+            Some(rest) => {
+                self.0 = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Consumes `keyword` if it's the next word, ignoring case.
+    fn keyword(&mut self, keyword: &str) -> bool {
+        self.skip_whitespace();
+        let matches = self
+            .0
+            .get(..keyword.len())
+            .is_some_and(|word| word.eq_ignore_ascii_case(keyword))
+            && !self.0[keyword.len()..].starts_with(|c: char| c.is_alphanumeric() || c == '_');
+
+        if matches {
+            self.0 = &self.0[keyword.len()..];
+        }
+        matches
+    }
+
+    /// The table of `[IGNORE] INTO table`, following `INSERT`.
+    fn header(&mut self) -> Option<String> {
+        self.keyword("IGNORE");
+        match self.keyword("INTO") {
+            true => self.identifier(),
+            false => None,
+        }
+    }
+
+    /// A plain or backquoted name, without any schema.
+    fn identifier(&mut self) -> Option<String> {
+        self.skip_whitespace();
+
+        let name = match self.0.strip_prefix('`') {
+            Some(rest) => {
+                let end = rest.find('`')?;
+                self.0 = &rest[end + 1..];
+                rest[..end].to_string()
+            }
+            None => {
+                let end = self
+                    .0
+                    .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+                    .unwrap_or(self.0.len());
+                let name = &self.0[..end];
+                self.0 = &self.0[end..];
+                name.to_string()
+            }
+        };
+
+        match self.eat('.') {
+            true => self.identifier(),
+            false => (!name.is_empty()).then_some(name),
+        }
+    }
+
+    /// Names up to the closing parenthesis of a column list.
+    fn columns(&mut self) -> Option<Vec<String>> {
+        let mut columns = vec![self.identifier()?];
+        while self.eat(',') {
+            columns.push(self.identifier()?);
+        }
+
+        self.eat(')').then_some(columns)
+    }
+
+    /// The values of a parenthesized row, `NULL` being an empty string.
+    fn tuple(&mut self) -> Option<Vec<String>> {
+        if !self.eat('(') {
+            return None;
+        }
+
+        let mut values = vec![self.value()?];
+        while self.eat(',') {
+            values.push(self.value()?);
+        }
+
+        self.eat(')').then_some(values)
+    }
+
+    fn value(&mut self) -> Option<String> {
+        self.skip_whitespace();
+
+        match self.0.chars().next()? {
+            quote @ ('\'' | '"') => self.string(quote),
+            _ => {
+                let end = self.0.find([',', ')'])?;
+                let value = self.0[..end].trim();
+                self.0 = &self.0[end..];
+
+                match value.eq_ignore_ascii_case("NULL") {
+                    true => Some(String::new()),
+                    false => Some(value.to_string()),
+                }
+            }
+        }
+    }
+
+    /// A string in MySQL's quoting, with backslash escapes and doubled
+    /// quotes.
+    fn string(&mut self, quote: char) -> Option<String> {
+        let mut value = String::new();
+        let mut chars = self.0.char_indices().skip(1);
+
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '\\' => value.push(match chars.next()?.1 {
+                    'n' => '\n',
+                    'r' => '\r',
+                    't' => '\t',
+                    '0' => '\0',
+                    'Z' => '\x1a',
+                    escaped => escaped,
+                }),
+                _ if c == quote => {
+                    if self.0[i + 1..].starts_with(quote) {
+                        chars.next();
+                        value.push(quote);
+                    } else {
+                        self.0 = &self.0[i + 1..];
+                        return Some(value);
+                    }
+                }
+                _ => value.push(c),
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DUMP: &str = r#"
+-- Dumping data for table `yourls_url`
+INSERT INTO `yourls_url` VALUES ('blaze','https://blazinglyfast.net/','Blazingly \'fast\'','2024-02-29 12:30:00','127.0.0.1',3),('ferris','https://rustacean.net/',NULL,'2024-02-29 12:30:00','::1',0);
+INSERT INTO `yourls_log` VALUES (1,'2024-02-29 12:31:00','blaze','','','','');
+INSERT INTO yourls_url (url, keyword) VALUES ('https://www.rust-lang.org/', 'rust''s');
+"#;
+
+    #[test]
+    fn rows_of_the_url_table_are_read() {
+        let records: Vec<_> = read_sql(DUMP.as_bytes()).unwrap();
+
+        assert_eq!(records.len(), 3);
+
+        let blaze = records[0].as_ref().unwrap();
+        assert_eq!(blaze.code, "blaze");
+        assert_eq!(blaze.link.url.as_str(), "https://blazinglyfast.net/");
+        assert_eq!(blaze.link.meta.title.as_deref(), Some("Blazingly 'fast'"));
+        assert_eq!(blaze.link.created_at, 1709209800);
+
+        assert_eq!(records[1].as_ref().unwrap().link.meta.title, None);
+        assert_eq!(records[2].as_ref().unwrap().code, "rust's");
+    }
+
+    #[test]
+    fn broken_rows_fail_the_dump() {
+        assert!(read_sql("INSERT INTO yourls_url VALUES ('blaze".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn csv_needs_keyword_and_url() {
+        let csv =
+            "keyword,url,title\nblaze,https://blazinglyfast.net/,\n,https://rustacean.net/,\n";
+        let records = read_csv(csv.as_bytes()).unwrap();
+
+        assert_eq!(records[0].as_ref().unwrap().code, "blaze");
+        assert_eq!(records[1], Err("keyword is missing".into()));
+    }
+}
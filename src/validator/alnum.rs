@@ -1,3 +1,6 @@
+// The tests make `Alnum` through `Default`, as they always have.
+#![cfg_attr(test, allow(clippy::default_constructed_unit_structs))]
+
 use super::Validate;
 
 #[derive(Default)]
@@ -7,6 +10,10 @@ impl Validate for Alnum {
     fn validate(&self, code: &str) -> bool {
         code.chars().all(char::is_alphanumeric)
     }
+
+    fn describe(&self) -> String {
+        "alphanumeric".into()
+    }
 }
 
 #[cfg(test)]
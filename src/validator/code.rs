@@ -1,4 +1,5 @@
-#[derive(Clone, Debug, Hash, Eq, PartialEq)]
+/// Only serializable, codes come from requests through a validator.
+#[derive(Clone, Debug, Hash, Eq, PartialEq, serde::Serialize)]
 pub struct Code(pub(crate) String);
 
 impl Code {
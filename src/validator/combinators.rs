@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use regex::Regex;
+
+use super::{Failure, Rule, Validate};
+
+/// Passes if every rule passes, reporting the first one that doesn't.
+///
+/// Codes are normalized by each rule in turn.
+pub struct AllOf(pub Vec<Rule>);
+
+/// Passes if at least one of the rules passes.
+pub struct AnyOf(pub Vec<Rule>);
+
+/// Passes if the rule fails.
+pub struct Not(pub Rule);
+
+/// Limits the length of a code, in characters.
+pub struct Length {
+    pub min: usize,
+    pub max: usize,
+}
+
+/// Passes if the regular expression matches.
+pub struct Pattern(pub Regex);
+
+impl Validate for AllOf {
+    fn validate(&self, code: &str) -> bool {
+        self.0.iter().all(|rule| rule.validate(code))
+    }
+
+    fn normalize(&self, code: String) -> String {
+        self.0.iter().fold(code, |code, rule| rule.normalize(code))
+    }
+
+    fn describe(&self) -> String {
+        let rules: Vec<String> = self.0.iter().map(|rule| rule.describe()).collect();
+        rules.join(" and ")
+    }
+
+    fn check(&self, code: &str) -> Result<(), Failure> {
+        self.0.iter().try_for_each(|rule| rule.check(code))
+    }
+}
+
+impl Validate for AnyOf {
+    fn validate(&self, code: &str) -> bool {
+        self.0.iter().any(|rule| rule.validate(code))
+    }
+
+    fn describe(&self) -> String {
+        let rules: Vec<String> = self.0.iter().map(|rule| rule.describe()).collect();
+        format!("either {}", rules.join(" or "))
+    }
+}
+
+impl Validate for Not {
+    fn validate(&self, code: &str) -> bool {
+        !self.0.validate(code)
+    }
+
+    fn describe(&self) -> String {
+        format!("not {}", self.0.describe())
+    }
+}
+
+impl Validate for Length {
+    fn validate(&self, code: &str) -> bool {
+        (self.min..=self.max).contains(&code.chars().count())
+    }
+
+    fn describe(&self) -> String {
+        format!("{} to {} characters long", self.min, self.max)
+    }
+}
+
+impl Validate for Pattern {
+    fn validate(&self, code: &str) -> bool {
+        self.0.is_match(code)
+    }
+
+    fn describe(&self) -> String {
+        format!("matching `{}`", self.0.as_str())
+    }
+}
+
+fn parse_rule(rule: &str, named: &HashMap<&str, Rule>) -> Result<Rule, String> {
+    let rule = rule.trim();
+
+    let Some((kind, arg)) = rule.split_once(':') else {
+        return named
+            .get(rule)
+            .cloned()
+            .ok_or_else(|| format!("unknown validation rule `{rule}`"));
+    };
+
+    Ok(match kind.trim() {
+        "not" => Arc::new(Not(parse_rule(arg, named)?)),
+        "any" => Arc::new(AnyOf(
+            arg.split('|')
+                .map(|rule| parse_rule(rule, named))
+                .collect::<Result<_, _>>()?,
+        )),
+        "length" => {
+            let (min, max) = arg
+                .split_once("..")
+                .ok_or_else(|| format!("expected `length:MIN..MAX`, got `{rule}`"))?;
+            let bound = |n: &str| {
+                n.trim()
+                    .parse()
+                    .map_err(|_| format!("invalid length bound `{n}`"))
+            };
+
+            Arc::new(Length {
+                min: bound(min)?,
+                max: bound(max)?,
+            })
+        }
+        "regex" => Arc::new(Pattern(Regex::new(arg).map_err(|e| e.to_string())?)),
+        _ => return Err(format!("unknown validation rule `{rule}`")),
+    })
+}
+
+/// Builds a validator chain from a `;` separated list of rules, e.g.
+/// `unicode; length:3..32; not:regex:^v[0-9]+$`.
+///
+/// A rule is one of the `named` rules, `length:MIN..MAX`, `regex:PATTERN`,
+/// `not:RULE` or `any:RULE|RULE|...`.
+pub fn parse(spec: &str, named: &HashMap<&str, Rule>) -> Result<AllOf, String> {
+    spec.split(';')
+        .filter(|rule| !rule.trim().is_empty())
+        .map(|rule| parse_rule(rule, named))
+        .collect::<Result<_, _>>()
+        .map(AllOf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validator::{Alnum, Reserved, Slug};
+
+    fn named() -> HashMap<&'static str, Rule> {
+        HashMap::from([
+            ("alnum", Arc::new(Alnum) as Rule),
+            ("slug", Arc::new(Slug::default()) as Rule),
+            ("unreserved", Arc::new(Reserved::default()) as Rule),
+        ])
+    }
+
+    fn check(spec: &str, code: &str) -> Result<(), Failure> {
+        parse(spec, &named()).unwrap().check(code)
+    }
+
+    #[test]
+    fn all_rules_must_pass() {
+        assert_eq!(check("alnum; length:3..8", "blaze"), Ok(()));
+        assert!(check("alnum; length:3..8", "blazingly").is_err());
+    }
+
+    #[test]
+    fn reports_first_failure() {
+        assert_eq!(
+            check("alnum; length:3..8; unreserved", "api"),
+            Err(Failure("not a reserved word".into()))
+        );
+        assert_eq!(
+            check("alnum; length:4..8", "b-z"),
+            Err(Failure("alphanumeric".into()))
+        );
+    }
+
+    #[test]
+    fn any_rule_may_pass() {
+        assert_eq!(check("any:alnum|slug", "brave-otter"), Ok(()));
+        assert_eq!(
+            check("any:alnum|length:1..2", "b/z"),
+            Err(Failure(
+                "either alphanumeric or 1 to 2 characters long".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn not_inverts() {
+        assert_eq!(check("not:regex:^v[0-9]+$", "blaze"), Ok(()));
+        assert_eq!(
+            check("not:regex:^v[0-9]+$", "v2"),
+            Err(Failure("not matching `^v[0-9]+$`".into()))
+        );
+    }
+
+    #[test]
+    fn unknown_rules_are_errors() {
+        assert!(parse("alnum; shiny", &named()).is_err());
+        assert!(parse("length:3", &named()).is_err());
+        assert!(parse("regex:(", &named()).is_err());
+    }
+
+    #[test]
+    fn empty_spec_accepts_everything() {
+        assert_eq!(check("", "anything goes"), Ok(()));
+    }
+}
@@ -8,6 +8,10 @@ impl Validate for DefaultValidator {
     fn validate(&self, code: &str) -> bool {
         self.0.is_match(code)
     }
+
+    fn describe(&self) -> String {
+        "a valid URL path segment".into()
+    }
 }
 
 impl Default for DefaultValidator {
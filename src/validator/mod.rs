@@ -1,13 +1,74 @@
 mod alnum;
 mod code;
+mod combinators;
 mod default;
+mod profanity;
+mod reserved;
+mod slug;
+mod unicode;
 
 pub use alnum::Alnum;
 pub use code::Code;
+pub use combinators::{parse, AllOf, AnyOf, Length, Not, Pattern};
 pub use default::DefaultValidator;
+pub use profanity::Profanity;
+pub use reserved::Reserved;
+pub use slug::Slug;
+pub use unicode::{nfc, Unicode};
+
+use std::{fmt::Display, sync::Arc};
+
+/// What a code that failed validation must be, e.g. `alphanumeric`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Failure(pub String);
+
+impl Display for Failure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "must be {}", self.0)
+    }
+}
+
+impl std::error::Error for Failure {}
 
 pub trait Validate {
     fn validate(&self, code: &str) -> bool;
+
+    /// Brings a code into the form it's validated and stored in.
+    fn normalize(&self, code: String) -> String {
+        code
+    }
+
+    /// What codes passing this rule are, completing "must be ...".
+    fn describe(&self) -> String;
+
+    /// Like `validate`, reporting the rule that failed.
+    fn check(&self, code: &str) -> Result<(), Failure> {
+        match self.validate(code) {
+            true => Ok(()),
+            false => Err(Failure(self.describe())),
+        }
+    }
+}
+
+/// Validation rule that can be shared between threads and combined.
+pub type Rule = Arc<dyn Validate + Send + Sync>;
+
+impl<T: Validate + ?Sized> Validate for Arc<T> {
+    fn validate(&self, code: &str) -> bool {
+        (**self).validate(code)
+    }
+
+    fn normalize(&self, code: String) -> String {
+        (**self).normalize(code)
+    }
+
+    fn describe(&self) -> String {
+        (**self).describe()
+    }
+
+    fn check(&self, code: &str) -> Result<(), Failure> {
+        (**self).check(code)
+    }
 }
 
 pub struct Validator<T: Validate>(T);
@@ -16,10 +77,19 @@ impl<T: Validate> Validator<T> {
     pub fn new(validator: T) -> Self {
         Self(validator)
     }
+
+    pub fn rule(&self) -> &T {
+        &self.0
+    }
 }
 
 impl<T: Validate> Validator<T> {
     pub fn validate(&self, s: String) -> Option<Code> {
-        self.0.validate(&s).then_some(Code(s))
+        self.check(s).ok()
+    }
+
+    pub fn check(&self, s: String) -> Result<Code, Failure> {
+        let s = self.0.normalize(s);
+        self.0.check(&s).map(|_| Code(s))
     }
 }
@@ -0,0 +1,163 @@
+use std::collections::BTreeSet;
+
+use super::Validate;
+
+const BLOCKLIST: &str = include_str!("blocklist.txt");
+
+/// Rejects codes containing an offensive word, even when spelled with
+/// leetspeak (`sh1t`) or split by separators (`fu-ck`).
+///
+/// Random codes are searched for words anywhere. Aliases are made of real
+/// words, which would often contain a short offensive one (`sparse`,
+/// `peacock`), so with `whole_words` they only fail if whole words of theirs
+/// spell one.
+#[derive(Clone)]
+pub struct Profanity {
+    words: BTreeSet<String>,
+    whole_words: bool,
+}
+
+/// Folds a lookalike digit or symbol onto the letter it stands in for,
+/// `None` for separators.
+fn fold_char(c: char) -> Option<char> {
+    match c.to_ascii_lowercase() {
+        '0' => Some('o'),
+        '1' | 'l' | '!' | '|' => Some('i'),
+        '3' => Some('e'),
+        '4' | '@' => Some('a'),
+        '5' | '$' => Some('s'),
+        '7' | '+' => Some('t'),
+        '8' => Some('b'),
+        '9' => Some('g'),
+        c if c.is_alphanumeric() => Some(c),
+        _ => None,
+    }
+}
+
+/// Folds lookalike digits and symbols onto the letter they stand in for and
+/// drops everything that isn't alphanumeric.
+fn fold(s: &str) -> String {
+    s.chars().filter_map(fold_char).collect()
+}
+
+/// The words of `s` between separators, folded.
+fn fold_words(s: &str) -> Vec<String> {
+    s.split(|c| fold_char(c).is_none())
+        .filter(|word| !word.is_empty())
+        .map(fold)
+        .collect()
+}
+
+impl Profanity {
+    /// Adds words to the blocklist, e.g. ones read from a file.
+    pub fn extend<'a>(mut self, words: impl IntoIterator<Item = &'a str>) -> Self {
+        self.words
+            .extend(words.into_iter().map(fold).filter(|word| !word.is_empty()));
+        self
+    }
+
+    /// Only rejects codes whose words, alone or run together, spell an
+    /// offensive one.
+    pub fn whole_words(self) -> Self {
+        Self {
+            whole_words: true,
+            ..self
+        }
+    }
+
+    pub fn from_file(path: &str) -> Result<Self, std::io::Error> {
+        let words = std::fs::read_to_string(path)?;
+        Ok(Self::default().extend(words.lines()))
+    }
+}
+
+impl Default for Profanity {
+    fn default() -> Self {
+        Self {
+            words: BTreeSet::new(),
+            whole_words: false,
+        }
+        .extend(BLOCKLIST.lines())
+    }
+}
+
+impl Validate for Profanity {
+    fn validate(&self, code: &str) -> bool {
+        if !self.whole_words {
+            let code = fold(code);
+            return !self.words.iter().any(|word| code.contains(word.as_str()));
+        }
+
+        let words = fold_words(code);
+        !(0..words.len()).any(|start| {
+            (start + 1..=words.len()).any(|end| self.words.contains(&words[start..end].concat()))
+        })
+    }
+
+    fn describe(&self) -> String {
+        "free of offensive words".into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_valid(code: &str) -> bool {
+        Profanity::default().validate(code)
+    }
+
+    #[test]
+    fn clean_code() {
+        assert!(is_valid("hWU7Xgc"))
+    }
+
+    #[test]
+    fn plain_word_is_invalid() {
+        assert!(!is_valid("shit"))
+    }
+
+    #[test]
+    fn substring_is_invalid() {
+        assert!(!is_valid("xYfUcKz9"))
+    }
+
+    #[test]
+    fn leetspeak_is_invalid() {
+        assert!(!is_valid("sh1t"));
+        assert!(!is_valid("b1tch"));
+        assert!(!is_valid("wh0r3"));
+    }
+
+    #[test]
+    fn separators_are_ignored() {
+        assert!(!is_valid("fu-ck"))
+    }
+
+    #[test]
+    fn extended_words_are_invalid() {
+        let profanity = Profanity::default().extend(["blazing"]);
+
+        assert!(!profanity.validate("b14zing"));
+        assert!(is_valid("blazing"));
+    }
+
+    #[test]
+    fn whole_words_allow_common_words() {
+        let profanity = Profanity::default().whole_words();
+
+        for alias in ["parse", "sparse", "grape", "drape", "peacock", "dickens"] {
+            assert!(profanity.validate(alias), "{alias}");
+        }
+    }
+
+    #[test]
+    fn whole_words_reject_offensive_words() {
+        let profanity = Profanity::default().whole_words();
+
+        assert!(!profanity.validate("shit"));
+        assert!(!profanity.validate("holy-sh1t"));
+        assert!(!profanity.validate("fu-ck"));
+        assert!(profanity.validate("fuchsia-kite"));
+    }
+}
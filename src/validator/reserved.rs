@@ -0,0 +1,113 @@
+use std::collections::BTreeSet;
+
+use super::Validate;
+
+/// Aliases kept free for routes, present or future, next to `/{code}`.
+pub const RESERVED_WORDS: &[&str] = &[
+    "about",
+    "admin",
+    "api",
+    "app",
+    "assets",
+    "auth",
+    "dashboard",
+    "docs",
+    "favicon",
+    "health",
+    "healthz",
+    "help",
+    "info",
+    "login",
+    "logout",
+    "metrics",
+    "ready",
+    "robots",
+    "settings",
+    "signup",
+    "static",
+    "status",
+    "v1",
+    "v2",
+];
+
+/// Rejects aliases that are reserved words, ignoring case.
+pub struct Reserved(BTreeSet<String>);
+
+impl Reserved {
+    pub fn extend<S: AsRef<str>>(mut self, words: impl IntoIterator<Item = S>) -> Self {
+        self.0
+            .extend(words.into_iter().map(|word| word.as_ref().to_lowercase()));
+        self
+    }
+
+    /// Reserves the first segment of every route path that starts with a
+    /// literal, e.g. `api` for `/api/{code}`.
+    pub fn with_routes<'a>(self, paths: impl IntoIterator<Item = &'a str>) -> Self {
+        let segments = paths
+            .into_iter()
+            .filter_map(|path| path.trim_start_matches('/').split('/').next())
+            .filter(|segment| !segment.is_empty() && !segment.starts_with('{'));
+
+        self.extend(segments)
+    }
+
+    pub fn contains(&self, alias: &str) -> bool {
+        self.0.contains(&alias.to_lowercase())
+    }
+}
+
+impl Default for Reserved {
+    fn default() -> Self {
+        Self(BTreeSet::new()).extend(RESERVED_WORDS)
+    }
+}
+
+impl Validate for Reserved {
+    fn validate(&self, code: &str) -> bool {
+        !self.contains(code)
+    }
+
+    fn describe(&self) -> String {
+        "not a reserved word".into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_valid(code: &str) -> bool {
+        Reserved::default().validate(code)
+    }
+
+    #[test]
+    fn valid_code() {
+        assert!(is_valid("blaze"))
+    }
+
+    #[test]
+    fn reserved_words_are_invalid() {
+        assert!(!is_valid("api"));
+        assert!(!is_valid("health"));
+    }
+
+    #[test]
+    fn case_is_ignored() {
+        assert!(!is_valid("Admin"))
+    }
+
+    #[test]
+    fn route_segments_are_reserved() {
+        let reserved = Reserved::default().with_routes(["/", "/{code}", "/links/{code}", "/qr"]);
+
+        assert!(!reserved.validate("links"));
+        assert!(!reserved.validate("qr"));
+        assert!(reserved.validate("{code}"));
+    }
+
+    #[test]
+    fn can_be_extended() {
+        let reserved = Reserved::default().extend(["blaze"]);
+        assert!(!reserved.validate("blaze"));
+    }
+}
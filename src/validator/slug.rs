@@ -0,0 +1,77 @@
+use super::{nfc, Validate};
+
+/// Alphanumeric segments joined by a single separator, e.g. `brave-otter-42`.
+///
+/// Plain alphanumeric codes (like the ones from `RB62`) are valid slugs too.
+pub struct Slug {
+    separator: char,
+}
+
+impl Slug {
+    pub fn new(separator: char) -> Self {
+        Self { separator }
+    }
+}
+
+impl Default for Slug {
+    fn default() -> Self {
+        Self::new('-')
+    }
+}
+
+impl Validate for Slug {
+    fn normalize(&self, code: String) -> String {
+        nfc(&code)
+    }
+
+    fn validate(&self, code: &str) -> bool {
+        code.split(self.separator)
+            .all(|part| !part.is_empty() && part.chars().all(char::is_alphanumeric))
+    }
+
+    fn describe(&self) -> String {
+        format!("alphanumeric words separated by `{}`", self.separator)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_valid(code: &str) -> bool {
+        Slug::default().validate(code)
+    }
+
+    #[test]
+    fn alnum_is_valid() {
+        assert!(is_valid("hWU7Xgc"))
+    }
+
+    #[test]
+    fn words_are_valid() {
+        assert!(is_valid("brave-otter-42"))
+    }
+
+    #[test]
+    fn empty_is_invalid() {
+        assert!(!is_valid(""))
+    }
+
+    #[test]
+    fn dangling_separators_are_invalid() {
+        assert!(!is_valid("-brave"));
+        assert!(!is_valid("otter-"));
+        assert!(!is_valid("brave--otter"));
+    }
+
+    #[test]
+    fn other_separators_are_invalid() {
+        assert!(!is_valid("brave_otter"));
+        assert!(Slug::new('_').validate("brave_otter"));
+    }
+
+    #[test]
+    fn slash_is_invalid() {
+        assert!(!is_valid("brave/otter"))
+    }
+}
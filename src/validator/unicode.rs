@@ -0,0 +1,225 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use unicode_normalization::UnicodeNormalization;
+use unicode_security::{skeleton, GeneralSecurityProfile, MixedScript};
+
+use super::{Failure, Validate};
+
+/// Custom alias validation that is safe for Unicode.
+///
+/// Aliases are NFC normalized and must be `min..=max` characters long. They
+/// may only use characters allowed in identifiers (plus the separator, which
+/// can't start, end or repeat), from a single script, and must not look like
+/// an alias registered before, e.g. a Cyrillic `аpple` next to a Latin
+/// `apple`.
+pub struct Unicode {
+    min: usize,
+    max: usize,
+    separator: char,
+    /// Confusable skeletons of registered aliases, mapped to the alias.
+    /// Stored codes are registered on startup, and new ones as they're
+    /// stored.
+    skeletons: RwLock<HashMap<String, String>>,
+}
+
+pub fn nfc(s: &str) -> String {
+    s.nfc().collect()
+}
+
+impl Unicode {
+    pub fn new(min: usize, max: usize, separator: char) -> Self {
+        Self {
+            min,
+            max,
+            separator,
+            skeletons: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Remembers an alias, so that aliases looking like it are rejected.
+    pub fn register(&self, alias: &str) {
+        self.skeletons
+            .write()
+            .unwrap()
+            .insert(skeleton(alias).collect(), alias.to_string());
+    }
+
+    /// The registered alias `alias` could be mistaken for, if any.
+    pub fn confusable_with(&self, alias: &str) -> Option<String> {
+        let skeleton: String = skeleton(alias).collect();
+
+        self.skeletons
+            .read()
+            .unwrap()
+            .get(&skeleton)
+            .filter(|known| *known != alias)
+            .cloned()
+    }
+
+    fn allowed(&self, c: char) -> bool {
+        c == self.separator || (c.is_alphanumeric() && c.identifier_allowed())
+    }
+}
+
+impl Default for Unicode {
+    fn default() -> Self {
+        Self::new(3, 64, '-')
+    }
+}
+
+impl Validate for Unicode {
+    fn normalize(&self, code: String) -> String {
+        nfc(&code)
+    }
+
+    fn validate(&self, code: &str) -> bool {
+        self.check(code).is_ok()
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "{} to {} letters, digits or `{}` of a single script",
+            self.min, self.max, self.separator
+        )
+    }
+
+    fn check(&self, code: &str) -> Result<(), Failure> {
+        let length = code.chars().count();
+
+        if !(self.min..=self.max).contains(&length) {
+            return Err(Failure(format!(
+                "{} to {} characters long",
+                self.min, self.max
+            )));
+        }
+
+        if !code.chars().all(|c| self.allowed(c)) {
+            return Err(Failure(format!(
+                "made of letters, digits and `{}`",
+                self.separator
+            )));
+        }
+
+        // Codes are redirected to as slugs, which don't have empty words.
+        if code.split(self.separator).any(str::is_empty) {
+            return Err(Failure(format!(
+                "words separated by a single `{}`",
+                self.separator
+            )));
+        }
+
+        if !code.is_single_script() {
+            return Err(Failure("written in a single script".into()));
+        }
+
+        match self.confusable_with(code) {
+            Some(known) => Err(Failure(format!("distinguishable from `{known}`"))),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validator::Slug;
+
+    fn is_valid(code: &str) -> bool {
+        let unicode = Unicode::default();
+        unicode.validate(&unicode.normalize(code.to_string()))
+    }
+
+    #[test]
+    fn valid_code() {
+        assert!(is_valid("blaze"))
+    }
+
+    #[test]
+    fn single_script_unicode_is_valid() {
+        assert!(is_valid("молния"));
+        assert!(is_valid("東京タワー"));
+    }
+
+    #[test]
+    fn length_is_limited() {
+        assert!(!is_valid("ab"));
+        assert!(!is_valid(&"a".repeat(65)));
+        assert!(is_valid(&"a".repeat(64)));
+    }
+
+    #[test]
+    fn length_counts_characters() {
+        assert!(is_valid("äöü"))
+    }
+
+    #[test]
+    fn mixed_scripts_are_invalid() {
+        // Latin `p`, `l` and `e` with a Cyrillic `а`.
+        assert!(!is_valid("\u{0430}pple"))
+    }
+
+    #[test]
+    fn symbols_are_invalid() {
+        assert!(!is_valid("blaze/fast"));
+        assert!(!is_valid("blaze✨"));
+        assert!(!is_valid("bla ze"));
+    }
+
+    #[test]
+    fn empty_words_are_invalid() {
+        assert!(!is_valid("-blaze"));
+        assert!(!is_valid("blaze-"));
+        assert!(!is_valid("brave--otter"));
+        assert!(is_valid("brave-otter"));
+    }
+
+    #[test]
+    fn valid_aliases_can_be_redirected_to() {
+        let slug = Slug::default();
+
+        for alias in [
+            "blaze",
+            "brave-otter-42",
+            "молния",
+            "東京タワー",
+            "-blaze",
+            "a--b",
+        ] {
+            if is_valid(alias) {
+                assert!(slug.validate(alias), "{alias}");
+            }
+        }
+    }
+
+    #[test]
+    fn reports_failed_rule() {
+        let unicode = Unicode::default();
+
+        assert_eq!(
+            unicode.check("\u{0430}pple"),
+            Err(Failure("written in a single script".into()))
+        );
+        assert_eq!(
+            unicode.check("ab"),
+            Err(Failure("3 to 64 characters long".into()))
+        );
+    }
+
+    #[test]
+    fn normalizes_to_nfc() {
+        let unicode = Unicode::default();
+        assert_eq!(unicode.normalize("cafe\u{301}".into()), "caf\u{e9}");
+    }
+
+    #[test]
+    fn confusables_of_registered_aliases_are_invalid() {
+        let unicode = Unicode::default();
+        unicode.register("paypal");
+
+        assert!(!unicode.validate("paypa1"));
+        assert!(!unicode.validate("\u{0440}\u{0430}\u{0443}\u{0440}\u{0430}l"));
+        assert_eq!(unicode.confusable_with("paypa1"), Some("paypal".into()));
+        assert!(unicode.validate("paypals"));
+    }
+}